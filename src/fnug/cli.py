@@ -1,33 +1,210 @@
+import json
+from collections.abc import Iterator
+from contextlib import contextmanager
+from dataclasses import dataclass
 from pathlib import Path
 
 import click
+from pydantic import ValidationError
 
 from fnug import FnugApp
-from fnug.config import load_config
+from fnug.config import (
+    Config,
+    ConfigFileError,
+    Theme,
+    config_schema,
+    duplicate_commands,
+    find_command,
+    format_config,
+    format_validation_error,
+    load_config,
+    load_config_dir,
+    validate_config,
+)
+from fnug.event_log import EventLog
+from fnug.foreground import run_foreground
 
 DEFAULT_FILE_NAMES = [".fnug.json", ".fnug.yaml", ".fnug.yml"]
 
 
-@click.command()
+@dataclass
+class CliContext:
+    """Options shared by all commands."""
+
+    config: str | None = None
+    config_dir: str | None = None
+    verbose: bool = False
+
+    def config_path(self) -> Path:
+        """Find the path of the config file (or directory)."""
+        if self.config_dir is not None:
+            return Path(self.config_dir)
+        if self.config is not None:
+            return Path(self.config)
+
+        for file_name in DEFAULT_FILE_NAMES:
+            if Path(file_name).exists():
+                return Path(file_name)
+        raise click.ClickException(f"Could not find a config file. Tried: {', '.join(DEFAULT_FILE_NAMES)}")
+
+    def load(self) -> tuple[Config, Path, Path]:
+        """Load the config, returns the config, the path of the config and the working directory."""
+        path = self.config_path()
+        try:
+            if self.config_dir is not None:
+                return load_config_dir(path), path, path
+            return load_config(path), path, path.parent
+        except ConfigFileError as e:
+            if self.verbose:
+                raise
+            raise click.ClickException(str(e)) from e
+        except ValidationError as e:
+            if self.verbose:
+                raise
+            raise click.ClickException(format_validation_error(e, path)) from e
+
+    @contextmanager
+    def handle_errors(self) -> Iterator[None]:
+        """Show exceptions as CLI errors (unless verbose)."""
+        try:
+            yield
+        except click.ClickException:
+            raise
+        except Exception as e:
+            if self.verbose:
+                raise
+            raise click.ClickException(str(e)) from e
+
+
+@click.group(invoke_without_command=True)
 @click.option("--config", "-c", type=click.Path(), help="Config file")
+@click.option(
+    "--config-dir",
+    type=click.Path(exists=True, file_okay=False),
+    help="Load all config files (.fnug.*) in a directory",
+)
+@click.option(
+    "--event-log",
+    type=click.Path(dir_okay=False),
+    help="Write a JSON lines log of command starts, stops and selections to a file",
+)
+@click.option(
+    "--event-log-max-bytes",
+    type=click.IntRange(min=1),
+    help="Rotate the event log when it grows larger than this (to <file>.1, <file>.2, ...)",
+)
+@click.option(
+    "--event-log-keep",
+    type=click.IntRange(min=0),
+    default=3,
+    show_default=True,
+    help="Number of rotated event logs to keep",
+)
+@click.option("--theme", type=click.Choice(["auto", "dark", "light"]), help="Color theme (overrides the config)")
+@click.option("--no-restore", is_flag=True, help="Don't restore (or save) the expanded groups and selected commands")
+@click.option("--print-config-path", is_flag=True, help="Print the path of the loaded config file and exit")
 @click.option("--verbose", "-v", is_flag=True, help="Verbose output")
 @click.version_option()
-def cli(config: str | None = None, verbose: bool = False) -> None:
+@click.pass_context
+def cli(
+    ctx: click.Context,
+    config: str | None = None,
+    config_dir: str | None = None,
+    event_log: str | None = None,
+    event_log_max_bytes: int | None = None,
+    event_log_keep: int = 3,
+    theme: Theme | None = None,
+    no_restore: bool = False,
+    print_config_path: bool = False,
+    verbose: bool = False,
+) -> None:
     """Entrypoint for the fnug CLI."""
+    if config is not None and config_dir is not None:
+        raise click.UsageError("--config and --config-dir can't be used together")
+
+    cli_context = ctx.obj = CliContext(config=config, config_dir=config_dir, verbose=verbose)
+    if ctx.invoked_subcommand is not None:
+        return
+
+    with cli_context.handle_errors():
+        cfg, file_path, cwd = cli_context.load()
+
+        if print_config_path:
+            click.echo(f"Config: {file_path.resolve()}")
+            click.echo(f"Working directory: {cwd.resolve()}")
+            return
+
+        FnugApp(
+            cfg,
+            cwd=cwd,
+            config_path=file_path,
+            event_log=EventLog(
+                Path(event_log) if event_log else None, max_bytes=event_log_max_bytes, keep=event_log_keep
+            ),
+            theme=theme,
+            restore=not no_restore,
+        ).run()
+
+
+@cli.command()
+@click.pass_obj
+def validate(cli_context: CliContext) -> None:
+    """Validate the config file, without starting the UI."""
+    path = cli_context.config_path()
     try:
-        if config is None:
-            for file_name in DEFAULT_FILE_NAMES:
-                if Path(file_name).exists():
-                    config = file_name
-                    break
-            else:
-                raise click.ClickException(f"Could not find a config file. Tried: {', '.join(DEFAULT_FILE_NAMES)}")
-
-        file_path = Path(config)
-        cwd = file_path.parent
-        cfg = load_config(file_path)
-        FnugApp(cfg, cwd=cwd).run()
+        cfg, _, cwd = cli_context.load()
     except Exception as e:
-        if verbose:
+        if cli_context.verbose:
             raise
-        raise click.ClickException(str(e)) from e
+        click.echo(f"{path} is invalid:\n{e}", err=True)
+        raise SystemExit(1) from e
+
+    for warning in duplicate_commands(cfg, cwd):
+        click.echo(f"Warning: {warning}", err=True)
+
+    problems = validate_config(cfg, cwd)
+    if problems:
+        click.echo(f"{path} is invalid:", err=True)
+        for problem in problems:
+            click.echo(f"  - {problem}", err=True)
+        raise SystemExit(1)
+
+    click.echo(f"{path} is valid")
+
+
+@cli.command()
+@click.option("--write", "-w", is_flag=True, help="Rewrite the config file, instead of printing it")
+@click.pass_obj
+def fmt(cli_context: CliContext, write: bool = False) -> None:
+    """Format the config file with sorted keys and consistent indentation (comments are not kept)."""
+    if cli_context.config_dir is not None:
+        raise click.UsageError("fmt formats a single config file, and can't be used with --config-dir")
+
+    path = cli_context.config_path()
+    with cli_context.handle_errors():
+        formatted = format_config(path)
+
+    if write:
+        path.write_text(formatted)
+    else:
+        click.echo(formatted, nl=False)
+
+
+@cli.command()
+def schema() -> None:
+    """Print the JSON schema of the config file, for editor autocompletion and validation."""
+    click.echo(json.dumps(config_schema(), indent=2))
+
+
+@cli.command()
+@click.argument("command")
+@click.pass_obj
+def run(cli_context: CliContext, command: str) -> None:
+    """Run a single command (by id or name) without the UI, and exit with its exit code."""
+    with cli_context.handle_errors():
+        cfg, _, cwd = cli_context.load()
+        config_command = find_command(cfg, command)
+
+    if config_command.cwd:
+        cwd = cwd / config_command.cwd
+    raise SystemExit(run_foreground(config_command, cwd, config_command.environment(cwd=cwd)))
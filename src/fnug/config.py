@@ -1,93 +1,754 @@
-from pathlib import Path
-from typing import Any, Literal
+import difflib
+import glob
+import json
+import os
+import re
+import shlex
+import shutil
+import subprocess
+from collections.abc import Iterator, Mapping
+from fnmatch import fnmatch
+from pathlib import Path, PurePath
+from typing import Annotated, Any, Literal, cast
 from uuid import uuid4
 
 import yaml
-from pydantic import BaseModel, Field, TypeAdapter, model_validator
+from pydantic import (
+    AfterValidator,
+    BaseModel,
+    Field,
+    PrivateAttr,
+    TypeAdapter,
+    ValidationError,
+    ValidationInfo,
+    model_validator,
+)
 
 
-class ConfigAuto(BaseModel):
+def _validate_regex(regex: list[str] | None) -> list[str] | None:
+    """Ensure that all regexes compile, reporting every invalid pattern at once."""
+    errors: list[str] = []
+    for pattern in regex or []:
+        try:
+            re.compile(pattern.removeprefix("!"))
+        except re.error as e:
+            errors.append(f"{pattern!r} ({e})")
+    if errors:
+        raise ValueError(f"Invalid regex: {', '.join(errors)}")
+    return regex
+
+
+Regex = Annotated[list[str] | None, AfterValidator(_validate_regex)]
+
+
+def regex_matches(regex: list[str], path: str) -> bool:
+    """
+    Check if a path matches a list of regexes, patterns prefixed with `!` are exclusions.
+
+    The patterns are applied in order, so an exclusion vetoes the patterns before it (and a later pattern can match
+    the path again), if there are only exclusions every other path matches.
+    """
+    matched = all(pattern.startswith("!") for pattern in regex)
+    for pattern in regex:
+        if pattern.startswith("!"):
+            matched = matched and not re.search(pattern[1:], path)
+        elif not matched:
+            matched = re.search(pattern, path) is not None
+    return matched
+
+
+_ENV_VAR = re.compile(r"\$(?:\$|([A-Za-z_][A-Za-z0-9_]*)|\{([A-Za-z_][A-Za-z0-9_]*)\})")
+
+
+def interpolate_env(value: str, env: Mapping[str, str]) -> str:
+    """
+    Expand `$VAR` and `${VAR}` references in a string (`$$` is a literal `$`).
+
+    Raises a KeyError with the name of the variable if it isn't set.
+    """
+
+    def replace(match: re.Match[str]) -> str:
+        name = match.group(1) or match.group(2)
+        if name is None:
+            return "$"
+        return env[name]
+
+    return _ENV_VAR.sub(replace, value)
+
+
+def _expand_glob(path: Path, cwd: Path) -> list[Path]:
+    """Expand a glob pattern (relative to cwd) into the matching paths, paths without a pattern are kept as is."""
+    pattern = path.as_posix()
+    if not any(char in pattern for char in "*?["):
+        return [path]
+    matches = sorted(glob.glob(pattern, root_dir=cwd))
+    if not matches:
+        raise ValueError(f"Auto path pattern {pattern} does not match any files or directories")
+    return [Path(match) for match in matches]
+
+
+class _ConfigModel(BaseModel):
+    """Base of the config models, unknown keys (e.g. typos) are rejected, suggesting the closest known key."""
+
+    @model_validator(mode="before")
+    @classmethod
+    def _reject_unknown_keys(cls, data: Any) -> Any:
+        if not isinstance(data, dict):
+            return data
+
+        known = list(cls.model_fields)
+        errors: list[str] = []
+        for key in cast(dict[Any, Any], data):
+            if key in known:
+                continue
+            suggestions = difflib.get_close_matches(str(key), known, n=1)
+            errors.append(f"{key!r} (did you mean {suggestions[0]!r}?)" if suggestions else repr(key))
+        if errors:
+            raise ValueError(f"Unknown keys: {', '.join(errors)}")
+        return data
+
+
+class ConfigWatchRule(_ConfigModel):
+    """A path to select commands based on, with its own regexes."""
+
+    path: Path
+    regex: Regex = None
+
+    def matches(self, cwd: Path, file: Path, root: Path | None = None) -> bool:
+        """
+        Check if a changed file is inside the path, and matches the regexes (if any).
+
+        The regexes are matched against the path relative to `root` (e.g. the git repository), or the absolute path if
+        `root` isn't given.
+        """
+        if cwd / self.path not in file.parents:
+            return False
+        target = file.relative_to(root) if root is not None and root in file.parents else file
+        return not self.regex or regex_matches(self.regex, target.as_posix())
+
+
+def _ignore_matches(path: PurePath, pattern: str) -> bool:
+    """Check if a path matches an ignore pattern, patterns without a slash can match any part of the path."""
+    pattern = pattern.rstrip("/")
+    if "/" in pattern:
+        return fnmatch(path.as_posix(), pattern) or fnmatch(path.as_posix(), f"{pattern}/*")
+    return any(fnmatch(part, pattern) for part in path.parts)
+
+
+RegexTarget = Literal["relative", "absolute"]
+
+
+class ConfigAuto(_ConfigModel):
     """Config for auto selecting/running commands."""
 
     git: bool | None = None
+    git_ref: str | None = None
+    staged: bool | None = None
     watch: bool | None = None
     always: bool | None = None
-    regex: list[str] | None = None
+    regex: Regex = None
+    regex_target: RegexTarget | None = None
     path: list[Path] | None = None
+    watch_rules: list[ConfigWatchRule] | None = None
+    ignore: list[str] | None = None
+    debounce_ms: int | None = Field(default=None, ge=0)
+    when: str | None = None
 
     def merge(self, other: "ConfigAuto"):
         """Merge two auto configs."""
         return ConfigAuto(
             git=self.git if self.git is not None else other.git,
+            git_ref=self.git_ref if self.git_ref is not None else other.git_ref,
+            staged=self.staged if self.staged is not None else other.staged,
             watch=self.watch if self.watch is not None else other.watch,
             always=self.always if self.always is not None else other.always,
             regex=self.regex if self.regex is not None else other.regex,
+            regex_target=self.regex_target if self.regex_target is not None else other.regex_target,
             path=self.path if self.path is not None else other.path,
+            watch_rules=self.watch_rules if self.watch_rules is not None else other.watch_rules,
+            ignore=self.ignore if self.ignore is not None else other.ignore,
+            debounce_ms=self.debounce_ms if self.debounce_ms is not None else other.debounce_ms,
+            when=self.when if self.when is not None else other.when,
         )
 
+    def expand_paths(self, env: Mapping[str, str], cwd: Path):
+        """Expand environment variables (see `interpolate_env`) and then glob patterns in the paths."""
+        if self.path is not None:
+            self.path = [
+                expanded
+                for path in self.path
+                for expanded in _expand_glob(Path(interpolate_env(path.as_posix(), env)), cwd)
+            ]
+        if self.watch_rules is not None:
+            self.watch_rules = [
+                ConfigWatchRule(path=expanded, regex=rule.regex)
+                for rule in self.watch_rules
+                for expanded in _expand_glob(Path(interpolate_env(rule.path.as_posix(), env)), cwd)
+            ]
+
+    def when_passes(self, cwd: Path) -> bool:
+        """Check if the `when` guard command exits successfully, True if there is no guard."""
+        if self.when is None:
+            return True
+        return subprocess.run(self.when, shell=True, cwd=cwd, capture_output=True).returncode == 0  # noqa: S602
+
+    def is_ignored(self, cwd: Path, file: Path) -> bool:
+        """Check if a changed file matches an ignore pattern, relative to the path of the rules it's inside."""
+        for rule in self.rules():
+            root = cwd / rule.path
+            if root in file.parents and any(_ignore_matches(file.relative_to(root), p) for p in self.ignore or []):
+                return True
+        return False
+
+    def rules(self) -> list[ConfigWatchRule]:
+        """Get all path rules, every path in `path` is a rule using the regexes in `regex`."""
+        return [*(ConfigWatchRule(path=path, regex=self.regex) for path in self.path or []), *(self.watch_rules or [])]
+
     @model_validator(mode="after")
     def ensure_path(self):
         """Ensure that path is set if git or watch is set."""
-        if self.git and not self.path:
-            raise ValueError("git auto requires path")
-        if self.watch and not self.path:
-            raise ValueError("watch auto requires path")
+        if self.git and not self.path and not self.watch_rules:
+            raise ValueError("git auto requires path or watch_rules")
+        if self.watch and not self.path and not self.watch_rules:
+            raise ValueError("watch auto requires path or watch_rules")
         return self
 
 
-class ConfigCommand(BaseModel):
+class ConfigCommand(_ConfigModel):
     """A command to run."""
 
     id: str = Field(default_factory=lambda: uuid4().hex)
     name: str
     cmd: str
+    disabled: bool = False
+    confirm: bool = False
+    before: str | None = None
+    after: str | None = None
     cwd: Path | None = None
+    shell: str | None = None
     interactive: bool = False
+    force_color: bool = True
+    max_line_length: int | None = Field(default=None, gt=0)
+    tab_width: int = Field(default=8, gt=0)
+    scrollback: int = Field(default=5000, gt=0)
+    kill_grace_ms: int = Field(default=3000, ge=0)
+    min_cols: int | None = Field(default=None, gt=0)
+    min_rows: int | None = Field(default=None, gt=0)
+    env: dict[str, str] = {}
+    env_clear: bool = False
+    env_passthrough: list[str] | None = None
+    env_file: list[Path] | None = None
+    path_prepend: list[Path] | None = None
+    requires: list[str] | None = None
     auto: ConfigAuto = ConfigAuto()
+    # the keys of `env` that were loaded from env files, which might be secrets
+    _env_file_keys: set[str] = PrivateAttr(default_factory=set)
+
+    def displayed_env(self) -> dict[str, str]:
+        """Get the `env` of the command for displaying, values loaded from env files are masked."""
+        return {key: "***" if key in self._env_file_keys else value for key, value in self.env.items()}
+
+    def environment(self, defaults: Mapping[str, str] | None = None, cwd: Path | None = None) -> dict[str, str]:
+        """
+        Get the environment variables of the command.
+
+        The environment of fnug is inherited, unless `env_clear` or `env_passthrough` is set, in which case only the
+        variables in `env_passthrough` are inherited. The command's own `env` takes precedence over the defaults.
+        The `path_prepend` entries (relative to `cwd`) are prepended to `PATH`.
+        """
+        if self.env_clear or self.env_passthrough is not None:
+            inherited = {key: os.environ[key] for key in self.env_passthrough or [] if key in os.environ}
+        else:
+            inherited = dict(os.environ)
+        env = {**inherited, **(defaults or {}), **self.env}
+        if self.path_prepend:
+            base = cwd or Path.cwd()
+            entries = [(base / path).as_posix() for path in self.path_prepend]
+            env["PATH"] = os.pathsep.join([*entries, env["PATH"]] if env.get("PATH") else entries)
+        return env
+
+    def shell_args(self, cmd: str | None = None) -> list[str] | None:
+        """
+        Get the arguments to run the command (or `cmd`, e.g. a hook) with its shell (e.g. `bash -eo pipefail`).
+
+        Returns None if the command uses the default shell.
+        """
+        if self.shell is None:
+            return None
+        return [*shlex.split(self.shell), "-c", cmd or self.cmd]
+
+    def run_subprocess(self, cmd: str, cwd: Path, env: Mapping[str, str]) -> int:
+        """Run the command (or `cmd`, e.g. a hook) with its shell in a subprocess, returns the exit code."""
+        shell_args = self.shell_args(cmd)
+        if shell_args is None:
+            return subprocess.run(cmd, shell=True, cwd=cwd, env=env).returncode  # noqa: S602
+        return subprocess.run(shell_args, cwd=cwd, env=env).returncode  # noqa: S603
+
+    def _interpolate_env(self, root: Path):
+        """
+        Expand environment variables in cwd and auto paths, the command's own `env` wins over fnug's.
+
+        This is done after the env of groups are merged into `env` (but before env files are loaded, as they're
+        relative to the cwd). The cmd and hooks are left as is, the shell expands them when they're run.
+        """
+        env = {**os.environ, **self.env}
+        try:
+            if self.cwd is not None:
+                self.cwd = Path(interpolate_env(self.cwd.as_posix(), env))
+            self.auto.expand_paths(env, root)
+        except KeyError as e:
+            raise ValueError(f"Command {self.name!r} uses environment variable {e.args[0]}, which is not set") from e
+
+    def _check_requires(self, root: Path):
+        """Ensure that the required executables are installed (on the PATH of the command, after it's inherited)."""
+        cwd = root / self.cwd if self.cwd else root
+        path = self.environment(cwd=cwd).get("PATH")
+        missing = [executable for executable in self.requires or [] if shutil.which(executable, path=path) is None]
+        if missing:
+            raise ValueError(f"Command {self.name!r} requires {', '.join(missing)}, which could not be found on PATH")
+
+
+def _context_cwd(info: ValidationInfo) -> Path:
+    """Get the config directory from the validation context."""
+    context = cast(dict[str, Any], info.context or {})
+    return context.get("cwd", Path.cwd())
+
+
+def _context_root(info: ValidationInfo) -> Path:
+    """Get the working directory (relative paths in the config are relative to it) from the validation context."""
+    context = cast(dict[str, Any], info.context or {})
+    return context.get("root", _context_cwd(info))
 
 
-class ConfigCommandGroup(BaseModel):
+def _structure_only(info: ValidationInfo) -> bool:
+    """Check if only the structure of the config is validated, without running generators or checking the host."""
+    context = cast(dict[str, Any], info.context or {})
+    return bool(context.get("structure_only"))
+
+
+def _run_generator(command: str, cwd: Path) -> list[Any]:
+    """Run a generator command, and parse its output as a list of command definitions."""
+    process = subprocess.run(command, shell=True, cwd=cwd, capture_output=True, text=True)  # noqa: S602
+    if process.returncode != 0:
+        message = f"Generator {command!r} failed with exit code {process.returncode}"
+        if process.stderr.strip():
+            message += f": {process.stderr.strip()}"
+        raise ValueError(message)
+    try:
+        output = json.loads(process.stdout)
+    except json.JSONDecodeError as e:
+        raise ValueError(f"Generator {command!r} did not output valid JSON: {e}") from e
+    if not isinstance(output, list):
+        raise ValueError(f"Generator {command!r} must output a JSON array of commands")
+    return cast(list[Any], output)
+
+
+def _read_config_file(path: Path) -> Any:
+    """Read the raw data of a config file (yaml or json), syntax errors include the line and column."""
+    if path.suffix in [".yaml", ".yml"]:
+        try:
+            return yaml.safe_load(Path.open(path, "rb").read())
+        except yaml.MarkedYAMLError as e:
+            mark = e.problem_mark or e.context_mark
+            position = f"{mark.line + 1}:{mark.column + 1}: " if mark else ""
+            raise ValueError(f"{path}:{position}{e.problem or e.context}") from e
+    try:
+        return json.loads(Path.open(path, "rb").read())
+    except json.JSONDecodeError as e:
+        raise ValueError(f"{path}:{e.lineno}:{e.colno}: {e.msg}") from e
+
+
+def _merge_paths(first: list[Path] | None, second: list[Path] | None) -> list[Path] | None:
+    """Merge two lists of paths (without duplicates), None if both are empty."""
+    return list(dict.fromkeys([*(first or []), *(second or [])])) or None
+
+
+def read_env_file(path: Path) -> dict[str, str]:
+    """Read a dotenv file (`KEY=value` lines, optionally quoted or prefixed with `export`, `#` for comments)."""
+    if not path.is_file():
+        raise ValueError(f"Env file {path} does not exist")
+
+    env: dict[str, str] = {}
+    for line in path.read_text().splitlines():
+        line = line.strip()
+        if not line or line.startswith("#"):
+            continue
+        key, separator, value = line.removeprefix("export ").partition("=")
+        if not separator:
+            raise ValueError(f"Invalid line in env file {path}: {line}")
+        value = value.strip()
+        if len(value) >= 2 and value[0] == value[-1] and value[0] in "\"'":
+            value = value[1:-1]
+        env[key.strip()] = value
+    return env
+
+
+class ConfigCommandGroup(_ConfigModel):
     """A group of commands or subgroups."""
 
     id: str = Field(default_factory=lambda: uuid4().hex)
     name: str
+    disabled: bool = False
     commands: list[ConfigCommand] = []
     children: list["ConfigCommandGroup"] = []
     auto: ConfigAuto = ConfigAuto()
+    shell: str | None = None
+    env: dict[str, str] = {}
+    env_override: bool | None = None
+    env_file: list[Path] | None = None
+    path_prepend: list[Path] | None = None
+    generate: str | None = None
+    include: list[Path] | None = None
+
+    @model_validator(mode="before")
+    @classmethod
+    def _include_files(cls, data: Any, info: ValidationInfo) -> Any:
+        """Load the included config files (relative to the including file) as subgroups."""
+        if not isinstance(data, dict):
+            return data
+        group = cast(dict[str, Any], data)
+        if not group.get("include"):
+            return group
+
+        context = cast(dict[str, Any], info.context or {})
+        stack: tuple[Path, ...] = context.get("includes", ())
+        children = list(group.get("children", []))
+        for include in group["include"]:
+            path = (_context_cwd(info) / include).resolve()
+            if path in stack:
+                cycle = " -> ".join(file.as_posix() for file in (*stack[stack.index(path) :], path))
+                raise ValueError(f"Include cycle detected: {cycle}")
+            if not path.is_file():
+                raise ValueError(f"Included file {include} does not exist")
+            include_context = {**context, "cwd": path.parent, "includes": (*stack, path)}
+            data = _read_config_file(path)
+            if isinstance(data, dict):
+                # included files might be complete configs, that can be used on their own
+                cast(dict[str, Any], data).pop("fnug_version", None)
+            children.append(ConfigCommandGroup.model_validate(data, context=include_context))
+        return {**group, "children": children}
+
+    @model_validator(mode="before")
+    @classmethod
+    def _generate_commands(cls, data: Any, info: ValidationInfo) -> Any:
+        """Append the commands outputted by the generator command (if any)."""
+        if not isinstance(data, dict):
+            return data
+        group = cast(dict[str, Any], data)
+        if not group.get("generate") or _structure_only(info):
+            return group
+        generated = _run_generator(group["generate"], _context_cwd(info))
+        return {**group, "commands": [*group.get("commands", []), *generated]}
+
+    def enabled_commands(self) -> list[ConfigCommand]:
+        """Get the commands that aren't disabled (none if the group is disabled)."""
+        return [] if self.disabled else [command for command in self.commands if not command.disabled]
+
+    def enabled_children(self) -> list["ConfigCommandGroup"]:
+        """Get the subgroups that aren't disabled (none if the group is disabled)."""
+        return [] if self.disabled else [child for child in self.children if not child.disabled]
+
+    def _inherit_env(self, env: dict[str, str]) -> dict[str, str]:
+        """Merge the env of the group into the env of a child, the child wins unless `env_override` is false."""
+        if self.env_override is False:
+            return {**env, **self.env}
+        return {**self.env, **env}
 
     def _propagate_auto(self):
-        """Propagate auto settings to all children."""
+        """Propagate auto (and shell, env and path) settings to all children, the child's `path_prepend` comes first."""
         for command in self.commands:
             command.auto = command.auto.merge(self.auto)
+            command.shell = command.shell if command.shell is not None else self.shell
+            command.env = self._inherit_env(command.env)
+            command.env_file = _merge_paths(self.env_file, command.env_file)
+            command.path_prepend = _merge_paths(command.path_prepend, self.path_prepend)
 
         for child in self.children:
             child.auto = child.auto.merge(self.auto)
+            child.shell = child.shell if child.shell is not None else self.shell
+            child.env = self._inherit_env(child.env)
+            child.env_override = child.env_override if child.env_override is not None else self.env_override
+            child.env_file = _merge_paths(self.env_file, child.env_file)
+            child.path_prepend = _merge_paths(child.path_prepend, self.path_prepend)
             child._propagate_auto()
 
+    def _load_env_files(self, root: Path):
+        """Load the env files of all enabled commands (relative to their cwd), the `env` entries takes precedence."""
+        for command in self.enabled_commands():
+            cwd = root / command.cwd if command.cwd else root
+            env: dict[str, str] = {}
+            for env_file in command.env_file or []:
+                env.update(read_env_file(cwd / env_file))
+            command._env_file_keys = set(env) - set(command.env)  # pyright: ignore reportPrivateUsage=false
+            command.env = {**env, **command.env}
+
+        for child in self.enabled_children():
+            child._load_env_files(root)
+
+    def _interpolate_env(self, root: Path):
+        """Expand environment variables in the paths of all enabled commands (and the auto paths they inherit)."""
+        for command in self.enabled_commands():
+            command._interpolate_env(root)  # pyright: ignore reportPrivateUsage=false
+
+        for child in self.enabled_children():
+            child._interpolate_env(root)
+
+    def _check_requires(self, root: Path):
+        """Ensure that the required executables of all enabled commands are installed."""
+        for command in self.enabled_commands():
+            command._check_requires(root)  # pyright: ignore reportPrivateUsage=false
+
+        for child in self.enabled_children():
+            child._check_requires(root)
+
     @model_validator(mode="after")
-    def _no_empty_groups(self):
-        """Ensure that groups are not empty."""
+    def _no_empty_groups(self, info: ValidationInfo):
+        """Ensure that groups are not empty (groups with a generator aren't checked if it wasn't run)."""
+        if self.generate and _structure_only(info):
+            return self
         if not self.commands and not self.children:
             raise ValueError("A group must have at least one command or subgroup.")
         return self
 
 
+def _validate_keybindings(keybindings: dict[str, str]) -> dict[str, str]:
+    """Ensure that no key is bound to multiple actions."""
+    actions: dict[str, str] = {}
+    errors: list[str] = []
+    for action, keys in keybindings.items():
+        for key in (key.strip() for key in keys.split(",")):
+            if key in actions:
+                errors.append(f"{key} is bound to both {actions[key]} and {action}")
+            actions.setdefault(key, action)
+    if errors:
+        raise ValueError(f"Conflicting keybindings: {', '.join(errors)}")
+    return keybindings
+
+
+Theme = Literal["auto", "dark", "light"]
+
+
+class ConfigMouse(_ConfigModel):
+    """Mouse settings of the tree."""
+
+    double_click_ms: int = Field(default=500, gt=0)
+    single_click_run: bool = False
+
+
 class Config(ConfigCommandGroup):
     """The root config object."""
 
     fnug_version: Literal["0.1.0"]
+    notify: bool = False
+    show_exit_code: bool = False
+    theme: Theme = "auto"
+    mouse: ConfigMouse = ConfigMouse()
+    keybindings: Annotated[dict[str, str], AfterValidator(_validate_keybindings)] = {}
 
     def model_post_init(self, __context: Any) -> None:
-        """Post-init hook to propagate auto settings."""
+        """
+        Post-init hook to propagate settings, expand environment variables, load env files and check requires.
+
+        Only the settings are propagated if only the structure is validated (see `format_config`).
+        """
         self._propagate_auto()
+        context = cast(dict[str, Any], __context or {})
+        if context.get("structure_only"):
+            return
+        root = context.get("root", context.get("cwd", Path.cwd()))
+        # env files are relative to the cwd of commands, so the paths are expanded before they're loaded
+        self._interpolate_env(root)
+        self._load_env_files(root)
+        self._check_requires(root)
+        if next(iter_commands(self), None) is None:
+            raise ValueError("All commands are disabled, at least one command must be enabled.")
 
 
 ConfigValidator = TypeAdapter(Config)
 
 
+def config_schema() -> dict[str, Any]:
+    """Get the JSON schema of the config file."""
+    return ConfigValidator.json_schema()
+
+
 def load_config(path: Path) -> Config:
     """Load a config file."""
+    context = {"cwd": path.parent, "root": path.parent, "includes": (path.resolve(),)}
+    return ConfigValidator.validate_python(_read_config_file(path), context=context)
+
+
+def iter_commands(group: ConfigCommandGroup) -> Iterator[ConfigCommand]:
+    """Iterate over all enabled commands in a group (and its subgroups)."""
+    yield from group.enabled_commands()
+    for child in group.enabled_children():
+        yield from iter_commands(child)
+
+
+def find_command(config: Config, query: str) -> ConfigCommand:
+    """Find a command by id, or by name if no command has the id."""
+    commands = list(iter_commands(config))
+    for command in commands:
+        if command.id == query:
+            return command
+
+    matches = [command for command in commands if command.name == query]
+    if not matches:
+        raise ValueError(f"Could not find a command with the id or name {query!r}")
+    if len(matches) > 1:
+        ids = ", ".join(command.id for command in matches)
+        raise ValueError(f"Multiple commands are named {query!r}, use the id instead ({ids})")
+    return matches[0]
+
+
+class _FormatDumper(yaml.SafeDumper):
+    """Yaml dumper indenting lists, and writing multiline strings as blocks."""
+
+    def increase_indent(self, flow: bool = False, indentless: bool = False) -> None:
+        """Never write indentless lists."""
+        return super().increase_indent(flow, False)
+
+
+def _represent_str(dumper: yaml.SafeDumper, data: str) -> yaml.ScalarNode:
+    return dumper.represent_scalar("tag:yaml.org,2002:str", data, style="|" if "\n" in data else None)
+
+
+_FormatDumper.add_representer(str, _represent_str)
+
+
+def format_config(path: Path) -> str:
+    """
+    Format a config file (yaml or json) with sorted keys and consistent indentation, the config must be valid.
+
+    Only the structure is validated, generators aren't run and the host isn't checked (e.g. `requires`, `env_file`).
+    """
+    data = _read_config_file(path)
+    context = {"cwd": path.parent, "root": path.parent, "includes": (path.resolve(),), "structure_only": True}
+    ConfigValidator.validate_python(data, context=context)
     if path.suffix in [".yaml", ".yml"]:
-        data = yaml.safe_load(Path.open(path, "rb").read())
-        return ConfigValidator.validate_python(data)
-    return ConfigValidator.validate_json(Path.open(path, "rb").read())
+        return yaml.dump(data, Dumper=_FormatDumper, sort_keys=True, indent=2, allow_unicode=True)
+    return json.dumps(data, indent=2, sort_keys=True, ensure_ascii=False) + "\n"
+
+
+def _node_position(node: yaml.Node | None, loc: tuple[int | str, ...]) -> tuple[int, int] | None:
+    """Find the line and column of a location (e.g. `("commands", 0, "cmd")`), or the closest parent that exists."""
+    if node is None:
+        return None
+
+    for key in loc:
+        child: yaml.Node | None = None
+        if isinstance(node, yaml.MappingNode):
+            child = next((value for name, value in node.value if name.value == key), None)
+        elif isinstance(node, yaml.SequenceNode) and isinstance(key, int) and key < len(node.value):
+            child = node.value[key]
+        if child is None:
+            break
+        node = child
+    return node.start_mark.line + 1, node.start_mark.column + 1
+
+
+def format_validation_error(error: ValidationError, path: Path) -> str:
+    """Format the errors of a config file, with the line and column (if it can be found) of each error."""
+    try:
+        root = yaml.compose(path.read_text())
+    except (OSError, yaml.YAMLError):
+        root = None
+
+    lines: list[str] = []
+    for details in error.errors():
+        position = _node_position(root, details["loc"])
+        location = f"{path}:{position[0]}:{position[1]}" if position else str(path)
+        field = ".".join(str(key) for key in details["loc"])
+        lines.append(f"{location}: {field + ': ' if field else ''}{details['msg']}")
+    return "\n".join(lines)
+
+
+def validate_config(config: Config, cwd: Path) -> list[str]:
+    """Find problems in a config that aren't caught while parsing it (duplicate ids, missing directories)."""
+    problems: list[str] = []
+    seen_ids: set[str] = set()
+
+    def check_id(item_id: str, name: str):
+        if item_id in seen_ids:
+            problems.append(f"{name}: duplicate id {item_id!r}")
+        seen_ids.add(item_id)
+
+    def check_auto(auto: ConfigAuto, name: str):
+        for rule in auto.rules():
+            if not (cwd / rule.path).exists():
+                problems.append(f"{name}: auto path {rule.path} does not exist")
+
+    def check_group(group: ConfigCommandGroup, parent_name: str | None, disabled: bool = False):
+        group_name = f"{parent_name} > {group.name}" if parent_name else group.name
+        check_id(group.id, group_name)
+        # ids of disabled commands must still be unique, but the rest of their settings aren't checked
+        disabled = disabled or group.disabled
+
+        for command in group.commands:
+            name = f"{group_name} > {command.name}"
+            check_id(command.id, name)
+            if disabled or command.disabled:
+                continue
+            command_cwd = cwd / command.cwd if command.cwd else cwd
+            if not command_cwd.is_dir():
+                problems.append(f"{name}: cwd {command.cwd} is not a directory")
+            check_auto(command.auto, name)
+
+        for child in group.children:
+            check_group(child, group_name, disabled)
+
+    check_group(config, None)
+    return problems
+
+
+def duplicate_commands(config: Config, cwd: Path) -> list[str]:
+    """Find commands with the same cwd and cmd (often a copy-paste mistake), these are warnings rather than errors."""
+    warnings: list[str] = []
+    seen: dict[tuple[Path, str], str] = {}
+
+    def check_group(group: ConfigCommandGroup, parent_name: str | None):
+        group_name = f"{parent_name} > {group.name}" if parent_name else group.name
+        for command in group.enabled_commands():
+            name = f"{group_name} > {command.name}"
+            key = ((cwd / command.cwd if command.cwd else cwd).resolve(), command.cmd)
+            if key in seen:
+                warnings.append(f"{name}: same cwd and cmd as {seen[key]}")
+            else:
+                seen[key] = name
+
+        for child in group.enabled_children():
+            check_group(child, group_name)
+
+    check_group(config, None)
+    return warnings
+
+
+class ConfigFileError(ValueError):
+    """A config file of a config directory is invalid, the message is formatted with `format_validation_error`."""
+
+    def __init__(self, path: Path, error: ValidationError):
+        super().__init__(format_validation_error(error, path))
+        self.path = path
+        self.error = error
+
+
+def _load_dir_child(path: Path) -> ConfigCommandGroup:
+    """Load a config file of a config directory as a group, only the root of the directory is post-processed."""
+    context = {"cwd": path.parent, "root": path.parent, "includes": (path.resolve(),)}
+    data = _read_config_file(path)
+    try:
+        # the file must be a complete config, but the root settings (e.g. `keybindings`) only apply to the root
+        ConfigValidator.validate_python(data, context={**context, "structure_only": True})
+        group = {key: value for key, value in data.items() if key in ConfigCommandGroup.model_fields}
+        return ConfigCommandGroup.model_validate(group, context=context)
+    except ValidationError as e:
+        raise ConfigFileError(path, e) from e
+
+
+def load_config_dir(path: Path) -> Config:
+    """Load all config files in a directory, as children of a single root group."""
+    files = sorted(file for file in path.glob(".fnug.*") if file.suffix in [".json", ".yaml", ".yml"])
+    if not files:
+        raise ValueError(f"Could not find any config files in {path}")
+    root = {"fnug_version": "0.1.0", "name": path.resolve().name, "children": [_load_dir_child(file) for file in files]}
+    return ConfigValidator.validate_python(root, context={"cwd": path, "root": path})
@@ -0,0 +1,39 @@
+import json
+from datetime import datetime, timezone
+from pathlib import Path
+from typing import Any
+
+
+class EventLog:
+    """Records the execution of commands (starts, stops, selections) as JSON lines, used for debugging."""
+
+    def __init__(self, path: Path | None = None, max_bytes: int | None = None, keep: int = 3):
+        self.path = path
+        self.max_bytes = max_bytes
+        self.keep = keep
+
+    def _rotate(self):
+        """Roll the log over to `<path>.1` (and `<path>.1` to `<path>.2` etc.), keeping `keep` old files."""
+        if self.path is None:
+            return
+
+        for index in range(self.keep - 1, 0, -1):
+            older = self.path.with_name(f"{self.path.name}.{index}")
+            if older.exists():
+                older.replace(self.path.with_name(f"{self.path.name}.{index + 1}"))
+        if self.keep:
+            self.path.replace(self.path.with_name(f"{self.path.name}.1"))
+        else:
+            self.path.unlink()
+
+    def write(self, event: str, **data: Any):
+        """Write an event (if the event log is enabled), rotating the file if it's larger than `max_bytes`."""
+        if self.path is None:
+            return
+
+        if self.max_bytes is not None and self.path.exists() and self.path.stat().st_size >= self.max_bytes:
+            self._rotate()
+
+        record = {"timestamp": datetime.now(timezone.utc).isoformat(), "event": event, **data}
+        with self.path.open("a") as file:
+            file.write(json.dumps(record, default=str) + "\n")
@@ -0,0 +1,36 @@
+from collections.abc import Mapping
+from pathlib import Path
+
+import click
+import rich
+
+from fnug.config import ConfigCommand
+from fnug.terminal_emulator import failure_message, start_message, success_message
+
+
+def run_foreground(command: ConfigCommand, cwd: Path, env: Mapping[str, str]) -> int:
+    """
+    Run a command in the current terminal (outside the UI), returns the exit code of the command.
+
+    The `before` hook failing aborts the command, while the `after` hook always runs, but doesn't change the exit code.
+    """
+    exit_code = 0
+    if command.before:
+        rich.print(start_message(command.before))
+        exit_code = command.run_subprocess(command.before, cwd, env)
+
+    if exit_code == 0:
+        rich.print(start_message(command.cmd))
+        exit_code = command.run_subprocess(command.cmd, cwd, env)
+
+    if exit_code == 0:
+        rich.print(success_message())
+    else:
+        rich.print(failure_message(exit_code))
+
+    if command.after:
+        rich.print(start_message(command.after))
+        after_exit_code = command.run_subprocess(command.after, cwd, env)
+        if after_exit_code != 0:
+            click.echo(f"The after hook failed (exit code {after_exit_code})", err=True)
+    return exit_code
@@ -1,8 +1,29 @@
-import re
 from functools import cache
 from pathlib import Path
 
-from pygit2 import Repository, discover_repository
+from pygit2 import (
+    GIT_STATUS_INDEX_DELETED,
+    GIT_STATUS_INDEX_MODIFIED,
+    GIT_STATUS_INDEX_NEW,
+    GIT_STATUS_INDEX_RENAMED,
+    GIT_STATUS_INDEX_TYPECHANGE,
+    Repository,
+    Tree,
+    discover_repository,
+)
+
+from fnug.config import regex_matches
+
+_STAGED_STATUS = (
+    GIT_STATUS_INDEX_NEW
+    | GIT_STATUS_INDEX_MODIFIED
+    | GIT_STATUS_INDEX_DELETED
+    | GIT_STATUS_INDEX_RENAMED
+    | GIT_STATUS_INDEX_TYPECHANGE
+)
+
+# The status of each repository (by repository path, ref and staged), shared by the calls of a single selection pass
+StatusCache = dict[tuple[str, str | None, bool], list[str]]
 
 
 @cache
@@ -13,16 +34,97 @@ def _get_repo(path: Path) -> Repository | None:
     return Repository(repo_path)
 
 
-def _git_status(path: Path) -> list[str]:
+def _resolve_ref(repo: Repository, ref: str) -> Tree:
+    try:
+        return repo.revparse_single(ref).peel(Tree)
+    except (KeyError, ValueError) as e:
+        raise ValueError(f"Could not resolve git ref {ref!r}") from e
+
+
+def _git_status(
+    path: Path,
+    ref: str | None = None,
+    staged: bool = False,
+    status_cache: StatusCache | None = None,
+) -> list[str]:
     repo = _get_repo(path)
     if repo is None:
-        raise ValueError(f"{path} is not inside a git repository")
-    return list(repo.status().keys())
+        # Not being inside a git repository isn't an error, as the config might only use `always` or `watch`
+        return []
 
+    key = (repo.path, ref, staged)
+    if status_cache is not None and key in status_cache:
+        return status_cache[key]
 
-def detect_repo_changes(path: Path, regex: list[str] | None = None) -> bool:
-    """Detect if a git repository has changes."""
-    files = _git_status(path)
+    status = repo.status()
+    if staged:
+        files = [file for file, flags in status.items() if flags & _STAGED_STATUS]
+    else:
+        files = list(status.keys())
+
+    if ref:
+        # Changes between the ref and the index (committed or staged), along with the changes from the status (e.g.
+        # unstaged or untracked files). A diff to the workdir would skip files added since the ref, as it doesn't use
+        # the index to tell which files are tracked
+        diff = _resolve_ref(repo, ref).diff_to_index(repo.index)
+        files = list(dict.fromkeys([*(delta.new_file.path for delta in diff.deltas), *files]))
+    if status_cache is not None:
+        status_cache[key] = files
+    return files
+
+
+def repo_root(path: Path) -> Path | None:
+    """Get the root (working directory) of the git repository a path is in, None if it isn't inside one."""
+    repo = _get_repo(path)
+    if repo is None or repo.workdir is None:
+        return None
+    return Path(repo.workdir)
+
+
+def is_git_ignored(repo_path: Path, path: Path) -> bool:
+    """Check if a path is ignored by git (e.g. in .gitignore), False if it isn't inside the git repository."""
+    repo = _get_repo(repo_path)
+    if repo is None or repo.workdir is None or Path(repo.workdir) not in path.parents:
+        return False
+    return repo.path_is_ignored(path.relative_to(repo.workdir).as_posix())
+
+
+def changed_files(
+    path: Path,
+    regex: list[str] | None = None,
+    ref: str | None = None,
+    staged: bool = False,
+    absolute: bool = False,
+    status_cache: StatusCache | None = None,
+) -> list[str]:
+    """
+    Get the changed files in a git repository (optionally only files matching a regex).
+
+    Changes are relative to HEAD, or `ref` if given, `staged` only includes changes in the index. There are no changes
+    if the path isn't inside a git repository. Only files inside the path are included. The files (and the path the
+    regexes are matched against) are relative to the root of the repository, unless `absolute` is set. The status of
+    the repository is reused from `status_cache` (and added to it), if given.
+    """
+    files = _git_status(path, ref, staged, status_cache)
+    root = repo_root(path)
+    if root is not None:
+        prefix = path.resolve().relative_to(root.resolve()).as_posix()
+        if prefix != ".":
+            files = [file for file in files if file == prefix or file.startswith(f"{prefix}/")]
+    if absolute and root is not None:
+        files = [(root / file).as_posix() for file in files]
     if regex:
-        files = [file for file in files if any(re.search(r, file) for r in regex)]
-    return len(files) >= 1
+        files = [file for file in files if regex_matches(regex, file)]
+    return files
+
+
+def detect_repo_changes(
+    path: Path,
+    regex: list[str] | None = None,
+    ref: str | None = None,
+    staged: bool = False,
+    absolute: bool = False,
+    status_cache: StatusCache | None = None,
+) -> bool:
+    """Detect if a git repository has changes."""
+    return len(changed_files(path, regex, ref, staged, absolute, status_cache)) >= 1
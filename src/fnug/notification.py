@@ -0,0 +1,19 @@
+import asyncio
+import shutil
+
+
+async def send_notification(title: str, message: str) -> bool:
+    """Send a desktop notification (using notify-send), returns False if notifications aren't available."""
+    notify_send = shutil.which("notify-send")
+    if notify_send is None:
+        return False
+
+    process = await asyncio.create_subprocess_exec(
+        notify_send,
+        "--app-name=fnug",
+        title,
+        message,
+        stdout=asyncio.subprocess.DEVNULL,
+        stderr=asyncio.subprocess.DEVNULL,
+    )
+    return await process.wait() == 0
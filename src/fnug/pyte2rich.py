@@ -6,8 +6,10 @@ from rich.style import Style
 from rich.text import Text
 
 
-def color_translator(color: str) -> str:
-    """Translate a pyte color to a rich color."""
+def color_translator(color: str) -> str | None:
+    """Translate a pyte color to a rich color, None for the default color (the color of the widget is used)."""
+    if color == "default":
+        return None
     if re.match("[0-9a-f]{6}", color, re.IGNORECASE):
         return f"#{color}"
 
@@ -16,12 +18,10 @@ def color_translator(color: str) -> str:
 
 def style_from_pyte(char: Char) -> Style:
     """Create a rich style from a pyte character."""
-    foreground = color_translator(char.fg)
-    background = "#1e1e1e" if char.bg == "default" else color_translator(char.bg)
-
+    # default colors are left unset, so the text and background colors of the widget (and theme) are used
     return Style(
-        color=foreground,
-        bgcolor=background,
+        color=color_translator(char.fg),
+        bgcolor=color_translator(char.bg),
         bold=char.bold,
         italic=char.italics,
         underline=char.underscore,
@@ -0,0 +1,30 @@
+from pathlib import Path
+
+from pydantic import BaseModel, ValidationError
+
+STATE_FILE_NAME = ".fnug-state.json"
+
+
+class TreeState(BaseModel):
+    """The expanded groups and selected commands of the tree, identified by their path of names."""
+
+    expanded: list[str] = []
+    selected: list[str] = []
+
+
+def state_path(config_path: Path) -> Path:
+    """Get the path of the state file, next to the config file (or inside the config directory)."""
+    return (config_path if config_path.is_dir() else config_path.parent) / STATE_FILE_NAME
+
+
+def load_state(path: Path) -> TreeState | None:
+    """Load the state of the tree, None if there is no (valid) state file."""
+    try:
+        return TreeState.model_validate_json(path.read_bytes())
+    except (OSError, ValidationError):
+        return None
+
+
+def save_state(path: Path, state: TreeState):
+    """Save the state of the tree."""
+    path.write_text(state.model_dump_json(indent=2) + "\n")
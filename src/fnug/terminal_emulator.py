@@ -1,21 +1,29 @@
 import asyncio
+import codecs
+import contextlib
 import fcntl
 import math
 import os
+import re
+import signal
 import struct
 import termios
-from collections.abc import AsyncIterable
+from collections.abc import AsyncIterable, Callable, Mapping, Sequence
 from pathlib import Path
-from typing import Literal
+from typing import Any, Literal
 
 import pyte
 from rich.console import Console
 from rich.text import Text
 from textual.geometry import Size
 
+from fnug.config import ConfigCommand
 from fnug.pyte2rich import pyte2rich
 
 
+FORCE_COLOR_ENV = {"FORCE_COLOR": "1", "CLICOLOR_FORCE": "1", "CLICOLOR": "1"}
+
+
 def start_message(commands: str) -> Text:
     """Create a start message for a command."""
     return Text.assemble(
@@ -44,18 +52,108 @@ def stopped_message() -> Text:
     return Text.assemble(Text("\n"), Text("❱ ", style="#cf6a4c"), Text("Stopped"), Text(" ✘", style="red"))
 
 
+def changed_files_message(files: list[str]) -> list[Text]:
+    """Create a message listing changed files."""
+    if not files:
+        return [Text.assemble(Text("❱ ", style="#cf6a4c"), Text("No changed files"))]
+    return [
+        Text.assemble(Text("❱ ", style="#cf6a4c"), Text("Changed files:")),
+        *[Text(f"  {file}", style="#808080") for file in files],
+    ]
+
+
 def any_key_message() -> Text:
     """Create a message to press any key."""
     return Text.assemble(Text("❱ ", style="#cf6a4c"), Text("Press any key to continue"))
 
 
+def _set_controlling_terminal():
+    """Make the tty the controlling terminal of the process, so it receives signals like SIGINT on ctrl+c."""
+    fcntl.ioctl(0, termios.TIOCSCTTY, 0)
+
+
+# Escape sequences (CSI, OSC, charset selection and two character sequences), these don't take up space on the line
+_ESCAPE = r"\x1b\[[0-?]*[ -/]*[@-~]|\x1b\][^\x07\x1b]*(?:\x07|\x1b\\)|\x1b[()#][0-~]|\x1b[0-Z\\^-~]"
+_LINE_TOKENS = re.compile(f"({_ESCAPE}|[\r\n])")
+# The start of an escape sequence, which might be continued in the next chunk of output
+_PARTIAL_ESCAPE = re.compile(r"\x1b(?:\[[0-?]*[ -/]*|\][^\x07\x1b]*\x1b?|[()#])?")
+
+
+class LineTruncator:
+    """Truncates lines longer than a max length, protects the terminal against pathological output (huge lines)."""
+
+    marker = " … (line truncated)"
+
+    def __init__(self, max_length: int | None = None):
+        self.max_length = max_length
+        self.line_length = 0
+        self.pending = ""
+
+    def _split_partial_escape(self, data: str) -> tuple[str, str]:
+        """Split an incomplete escape sequence from the end of a chunk, so it's handled with the next chunk."""
+        start = data.rfind("\x1b")
+        if start > 0 and data[start:] == "\x1b":
+            # the escape might be the end of an OSC sequence (ESC \)
+            osc = data.rfind("\x1b", 0, start)
+            if osc != -1 and _PARTIAL_ESCAPE.fullmatch(data, osc):
+                start = osc
+        if start != -1 and _PARTIAL_ESCAPE.fullmatch(data, start):
+            return data[:start], data[start:]
+        return data, ""
+
+    def feed(self, data: str) -> str:
+        """
+        Truncate the lines of a chunk of output, the length of the current line is kept between chunks.
+
+        Escape sequences (e.g. colors) don't count towards the length, and are kept after the line is truncated.
+        """
+        if self.max_length is None:
+            return data
+
+        data, self.pending = self._split_partial_escape(self.pending + data)
+        output: list[str] = []
+        for part in _LINE_TOKENS.split(data):
+            if part in ("\r", "\n"):
+                self.line_length = 0
+                output.append(part)
+                continue
+            if part.startswith("\x1b") and re.fullmatch(_ESCAPE, part):
+                output.append(part)
+                continue
+
+            remaining = self.max_length - self.line_length
+            if remaining > 0:
+                output.append(part[:remaining])
+                if len(part) > remaining:
+                    output.append(self.marker)
+            self.line_length += len(part)
+        return "".join(output)
+
+
 class FixedHistoryScreen(pyte.HistoryScreen):
     """
     Exactly like pyte.HistoryScreen but allows scrolling to the top of the buffer.
 
     This is done by loosening the condition for when to allow scrolling up.
+    It also allows changing the tab width (the default tab stops).
     """
 
+    def __init__(self, columns: int, lines: int, history: int, ratio: float, tab_width: int = 8):
+        self.tab_width = tab_width
+        super().__init__(columns, lines, history=history, ratio=ratio)
+
+    def reset(self) -> None:
+        """Reset the terminal, with tab stops every `tab_width` columns."""
+        super().reset()
+        self.tabstops = set(range(self.tab_width, self.columns, self.tab_width))
+
+    def resize(self, lines: int | None = None, columns: int | None = None) -> None:
+        """Resize the screen, adding default tab stops to new columns."""
+        old_columns = self.columns
+        super().resize(lines, columns)
+        new_stops = range(self.tab_width, self.columns, self.tab_width)
+        self.tabstops.update(stop for stop in new_stops if stop >= old_columns)
+
     def prev_page(self) -> None:
         """Scroll the screen up by one page."""
         if self.history.top:
@@ -75,13 +173,31 @@ class FixedHistoryScreen(pyte.HistoryScreen):
 class TerminalEmulator:
     """A terminal emulator."""
 
-    def __init__(self, dimensions: Size, can_focus: bool = False):
+    def __init__(
+        self,
+        dimensions: Size,
+        can_focus: bool = False,
+        max_line_length: int | None = None,
+        tab_width: int = 8,
+        scrollback: int = 5000,
+        kill_grace: float = 3.0,
+        min_size: Size | None = None,
+    ):
+        self.min_size = min_size or Size(0, 0)
+        dimensions = self._clamp(dimensions)
         self.pty, self.tty = os.openpty()
         self.out = os.fdopen(self.pty, "r+b", 0)
-        self.screen = FixedHistoryScreen(dimensions.width, dimensions.height, history=5000, ratio=0.25)
+        self.screen = FixedHistoryScreen(
+            dimensions.width, dimensions.height, history=scrollback, ratio=0.25, tab_width=tab_width
+        )
         self.stream = pyte.Stream(self.screen)
+        self.line_truncator = LineTruncator(max_line_length)
+        # characters can be split between reads, so the output is decoded incrementally
+        self.decoder = codecs.getincrementaldecoder("utf-8")(errors="replace")
         self.update_ready = asyncio.Event()
         self.finished = asyncio.Event()
+        self.exit_code: int | None = None
+        self.kill_grace = kill_grace
         self.dimensions = dimensions
         self.can_focus = can_focus
 
@@ -110,51 +226,129 @@ class TerminalEmulator:
         self.screen.dirty.clear()
         self.update_ready.set()
 
-    async def run_shell(self, command: str, cwd: Path) -> bool:
-        """Run a shell command in a subprocess, and send the output to the tty."""
+    async def run_shell(
+        self,
+        command: str,
+        cwd: Path,
+        env: Mapping[str, str] | None = None,
+        shell_args: Sequence[str] | None = None,
+    ) -> bool:
+        """
+        Run a shell command in a subprocess, and send the output to the tty (env defaults to the current env).
+
+        The command is run with the default shell, unless `shell_args` (the full arguments to run) is given.
+        """
         # Echo command to tty
         self.echo(start_message(command))
 
         loop = asyncio.get_running_loop()
 
-        def on_output():
-            self.stream.feed(self.out.read(65536).decode())
-            self.screen.dirty.clear()
-            self.update_ready.set()
-
-        loop.add_reader(self.out, on_output)
-
-        process = await asyncio.subprocess.create_subprocess_shell(
-            command,
-            cwd=cwd,
-            stdin=self.tty,
-            start_new_session=True,
-            stdout=self.tty,
-            stderr=self.tty,
-            env={**os.environ, "TERM": "xterm-256color"},
-        )
+        loop.add_reader(self.out, lambda: self.feed(self.out.read(65536)))
+
+        options: dict[str, Any] = {
+            "cwd": cwd,
+            "stdin": self.tty,
+            "start_new_session": True,
+            "preexec_fn": _set_controlling_terminal,
+            "stdout": self.tty,
+            "stderr": self.tty,
+            "env": {**(os.environ if env is None else env), "TERM": "xterm-256color"},
+        }
+        if shell_args is None:
+            process = await asyncio.subprocess.create_subprocess_shell(command, **options)
+        else:
+            process = await asyncio.subprocess.create_subprocess_exec(*shell_args, **options)
         try:
             code = await process.wait()
         except asyncio.CancelledError:
-            process.terminate()
-            await process.wait()
+            await self._kill(process)
             raise
         finally:
             loop.remove_reader(self.out)
 
+        self.exit_code = code
         if code == 0:
             self.echo(success_message())
         else:
             self.echo(failure_message(code))
+        return code == 0
 
+    async def run_with_hooks(
+        self,
+        command: ConfigCommand,
+        cwd: Path,
+        env: Mapping[str, str] | None = None,
+        on_done: Callable[[bool], None] | None = None,
+        on_after_failed: Callable[[], None] | None = None,
+    ) -> bool:
+        """
+        Run a command between its `before` and `after` hooks, returns if both the before hook and the command succeeded.
+
+        The command is skipped if the before hook fails, `on_done` is called with the result before the after hook
+        runs. The after hook always runs (shielded, also if the command is stopped), but doesn't change the result.
+        """
+        success = True
+        try:
+            if command.before:
+                before_args = command.shell_args(command.before)
+                success = await self.run_shell(command.before, cwd, env=env, shell_args=before_args)
+            if success:
+                success = await self.run_shell(command.cmd, cwd, env=env, shell_args=command.shell_args())
+            if on_done is not None:
+                on_done(success)
+        finally:
+            if command.after:
+                after_args = command.shell_args(command.after)
+                after = self.run_shell(command.after, cwd, env=env, shell_args=after_args)
+                if not await asyncio.shield(after) and on_after_failed is not None:
+                    on_after_failed()
+        return success
+
+    async def _kill(self, process: asyncio.subprocess.Process):
+        """Stop a process (and its children), with SIGTERM and then SIGKILL if it's still running after the grace."""
+        with contextlib.suppress(ProcessLookupError):
+            os.killpg(process.pid, signal.SIGTERM)
+        try:
+            await asyncio.wait_for(process.wait(), self.kill_grace)
+        except asyncio.TimeoutError:
+            with contextlib.suppress(ProcessLookupError):
+                os.killpg(process.pid, signal.SIGKILL)
+            await process.wait()
+
+    def feed(self, data: bytes):
+        """Feed output of the command to the terminal."""
+        self.stream.feed(self.line_truncator.feed(self.decoder.decode(data)))
+        self.screen.dirty.clear()
+        self.update_ready.set()
+
+    def finish(self):
+        """Mark the command as finished, called once all processes (e.g. the command and its hooks) have exited."""
         self.finished.set()
-        return code == 0
+
+    @property
+    def running(self) -> bool:
+        """Whether the command is still running, until `finish` is called."""
+        return not self.finished.is_set()
 
     def clear(self):
         """Clear the terminal."""
         self.screen.reset()
         self.update_ready.set()
 
+    def text(self) -> str:
+        """Get the full output (scrollback and screen) as plain text."""
+        columns = self.screen.columns
+
+        def line_text(line: dict[int, Any]) -> str:
+            return "".join(line[x].data for x in range(columns)).rstrip()
+
+        lines = [
+            *(line_text(line) for line in self.screen.history.top),
+            *(line.rstrip() for line in self.screen.display),
+            *(line_text(line) for line in self.screen.history.bottom),
+        ]
+        return "\n".join(lines).rstrip("\n") + "\n"
+
     def write(self, data: bytes):
         """Write data to the terminal."""
         os.write(self.pty, data)
@@ -174,6 +368,10 @@ class TerminalEmulator:
         self.screen.dirty.clear()
         self.update_ready.set()
 
+    def _clamp(self, dimensions: Size) -> Size:
+        """Never make the terminal smaller than the minimum size, the display is cropped instead."""
+        return Size(max(dimensions.width, self.min_size.width), max(dimensions.height, self.min_size.height))
+
     @property
     def dimensions(self):
         """The dimensions of the terminal."""
@@ -181,7 +379,9 @@ class TerminalEmulator:
 
     @dimensions.setter
     def dimensions(self, dimensions: Size):
+        dimensions = self._clamp(dimensions)
         self._dimensions = dimensions
         winsize = struct.pack("HH", dimensions.height, dimensions.width)
-        fcntl.ioctl(self.pty, termios.TIOCSWINSZ, winsize)
+        with contextlib.suppress(OSError):  # the pty might be unusable after the process exited
+            fcntl.ioctl(self.pty, termios.TIOCSWINSZ, winsize)
         self.screen.resize(dimensions.height, dimensions.width)
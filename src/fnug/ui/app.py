@@ -1,40 +1,55 @@
-import subprocess
-from collections.abc import Callable
+import os
+import re
+import shlex
+import time
+from collections.abc import Callable, Iterable
 from dataclasses import dataclass
+from datetime import datetime
 from functools import partial
 from pathlib import Path
 from typing import ClassVar
 
 import click
 import rich
+from pygit2 import GitError
 from textual import events, on
 from textual.app import App, ComposeResult
 from textual.binding import Binding, BindingType
 from textual.command import Hit, Hits, Provider
 from textual.containers import Horizontal
+from textual.geometry import Size
 from textual.widgets import Footer
 from textual.widgets._tree import TreeNode
 from textual.worker import Worker
 
-from fnug.config import Config
+from fnug.config import Config, Theme, duplicate_commands
+from fnug.event_log import EventLog
+from fnug.foreground import run_foreground
+from fnug.git import changed_files
+from fnug.notification import send_notification
+from fnug.state import load_state, state_path
 from fnug.terminal_emulator import (
+    FORCE_COLOR_ENV,
     TerminalEmulator,
     any_key_message,
-    failure_message,
-    start_message,
+    changed_files_message,
     stopped_message,
-    success_message,
 )
+from fnug.ui.components.command_info import CommandInfo
+from fnug.ui.components.confirm_run import ConfirmRun
 from fnug.ui.components.context_menu import ContextMenu
+from fnug.ui.components.group_view import GroupView
 from fnug.ui.components.lint_tree import (
     LintTree,
     LintTreeDataType,
     all_commands,
+    select_node,
     sum_selected_commands,
     toggle_select_node,
     update_node,
 )
 from fnug.ui.components.terminal import Terminal
+from fnug.ui.keybindings import binding_conflicts, rebind
 
 
 class _CommandProvider(Provider):
@@ -48,7 +63,7 @@ class _CommandProvider(Provider):
         self.commands = app.lint_tree.command_leafs
 
     async def search(self, query: str) -> Hits:
-        """Search for Python files."""
+        """Search for commands, fuzzy matching their name or id."""
         app = self.app
         if not isinstance(app, FnugApp):
             return
@@ -59,7 +74,7 @@ class _CommandProvider(Provider):
             if not node.data:
                 continue
 
-            score = matcher.match(node_id)
+            score = max(matcher.match(node.data.name), matcher.match(node_id))
             if score > 0:
                 callback: partial[Callable[[], None]] = partial(app.display_terminal, node_id)
                 yield Hit(
@@ -79,30 +94,106 @@ class TerminalInstance:
     run_task: Worker[None]
 
 
+# Commands running for at least this long send a notification when finished (if enabled)
+NOTIFY_AFTER_SECONDS = 2.0
+
+
+def _is_dark_background() -> bool:
+    """Guess if the terminal has a dark background, using COLORFGBG (set by some terminals), defaults to dark."""
+    background = os.environ.get("COLORFGBG", "").split(";")[-1]
+    return not background.isdigit() or int(background) not in (7, 15)
+
+
 class FnugApp(App[None]):
     """A Textual app to manage stopwatches."""
 
     COMMANDS: ClassVar[set[type[Provider] | Callable[[], type[Provider]]]] = {_CommandProvider}
     CSS_PATH = "app.tcss"
 
-    BINDINGS: ClassVar[list[BindingType]] = [Binding("escape", "quit", "Quit", show=False)]
+    BINDINGS: ClassVar[list[BindingType]] = [
+        Binding("escape", "quit", "Quit", show=False),
+        Binding("ctrl+c", "interrupt", "Quit", show=False, priority=True),
+        Binding("full_stop", "rerun_last", "Re-run last commands", show=False),
+        Binding("v", "toggle_group_view", "Toggle group view", show=False),
+        Binding("C", "clear_all", "Clear all terminals", show=False),
+        Binding("F", "rerun_failures", "Re-run failed commands", show=False),
+        Binding("m", "toggle_mouse", "Toggle mouse capture", show=False),
+        Binding("i", "show_info", "Show command info", show=False),
+    ]
 
     terminals: ClassVar[dict[str, TerminalInstance]] = {}
     active_terminal_id: str | None = None
     display_task: Worker[None] | None = None
-
-    def __init__(self, config: Config, cwd: Path | None = None):
+    group_view_id: str | None = None
+    mouse_captured: bool = True
+
+    def __init__(
+        self,
+        config: Config,
+        cwd: Path | None = None,
+        config_path: Path | None = None,
+        event_log: EventLog | None = None,
+        theme: Theme | None = None,
+        restore: bool = True,
+    ):
         super().__init__()
         self.cwd = (cwd or Path.cwd()).resolve()
         self.config = config
+        self.config_path = config_path.resolve() if config_path else None
+        self.event_log = event_log or EventLog()
+        self.last_run_ids: list[str] = []
+        self.color_theme = theme or config.theme
+        # The expanded groups and selected commands are persisted next to the config
+        self.state_path = state_path(self.config_path) if restore and self.config_path else None
 
     def compose(self) -> ComposeResult:
         """Create child widgets for the app."""
         with Horizontal(id="main"):
-            yield LintTree(self.config, cwd=self.cwd, id="lint-tree", classes="custom-scrollbar")
+            yield LintTree(
+                self.config,
+                cwd=self.cwd,
+                event_log=self.event_log,
+                state=load_state(self.state_path) if self.state_path else None,
+                state_path=self.state_path,
+                id="lint-tree",
+                classes="custom-scrollbar",
+            )
             yield Terminal(id="terminal", classes="custom-scrollbar")
+            yield GroupView(id="group-view")
         yield Footer()
 
+    def on_mount(self):
+        """Log which config was loaded (warning about duplicate commands), and apply the theme and keybindings."""
+        self.log.info(f"Loaded config {self.config_path} (working directory {self.cwd})")
+        for warning in duplicate_commands(self.config, self.cwd):
+            self.log.warning(warning)
+            self.notify(warning, title="Duplicate command", severity="warning")
+        self.dark = _is_dark_background() if self.color_theme == "auto" else self.color_theme == "dark"
+
+        keybindings = self.config.keybindings
+        # conflicting keys keep their default action, instead of silently replacing it
+        conflicts = binding_conflicts([self, self.lint_tree], keybindings)
+        if conflicts:
+            self.notify(f"Conflicting keybindings: {', '.join(conflicts.values())}", severity="error")
+        bound = rebind(self, keybindings, conflicts) | rebind(self.lint_tree, keybindings, conflicts)
+        unknown = set(keybindings) - bound
+        if unknown:
+            self.notify(f"Unknown actions in keybindings: {', '.join(sorted(unknown))}", severity="error")
+
+    def on_resize(self, event: events.Resize):
+        """Resize the terminals of all commands (not only the displayed one), so their output wraps correctly."""
+        self.call_after_refresh(self._resize_terminals)
+
+    def _resize_terminals(self):
+        size = self._terminal_size
+        if size.width <= 0 or size.height <= 0:
+            return
+
+        for command_id, instance in self.terminals.items():
+            # terminals in the group view are resized by their own widget
+            if command_id not in self._group_view.terminals and instance.emulator.dimensions != size:
+                instance.emulator.dimensions = size
+
     @property
     def lint_tree(self) -> LintTree:
         """The lint tree."""
@@ -112,6 +203,18 @@ class FnugApp(App[None]):
     def _terminal(self) -> Terminal:
         return self.query_one("#terminal", Terminal)
 
+    @property
+    def _group_view(self) -> GroupView:
+        return self.query_one("#group-view", GroupView)
+
+    @property
+    def _terminal_size(self) -> Size:
+        """The size of new terminals, the terminal is hidden while the group view is displayed."""
+        if self.group_view_id is None:
+            return self._terminal.size
+        size = self._group_view.size
+        return Size(width=size.width - 1, height=size.height)
+
     @on(LintTree.NodeHighlighted, "#lint-tree")
     def _switch_terminal(self, event: LintTree.NodeHighlighted[LintTreeDataType]):
         if event.node.data is not None:
@@ -141,11 +244,67 @@ class FnugApp(App[None]):
 
     @on(LintTree.RunAllCommand, "#lint-tree")
     def _run_all(self, event: LintTree.RunAllCommand):
-        cursor_id = getattr(self.lint_tree.cursor_node, "id", None)
+        self._run_commands(event.nodes)
 
-        for node in event.nodes:
-            if node.data is not None:
-                self._run_command(node.data, background=cursor_id != node.id)
+    async def action_interrupt(self) -> None:
+        """Interrupt the command of the focused terminal, or quit if no running command is focused."""
+        focused = self.focused
+        if isinstance(focused, Terminal) and focused.emulator and focused.emulator.running:
+            focused.emulator.write(b"\x03")
+        else:
+            await self.action_quit()
+
+    def action_clear_all(self) -> None:
+        """Clear and remove the terminals of all finished commands, running commands are kept."""
+        self._clear_finished(self.lint_tree.command_leafs.values())
+
+    def _clear_finished(self, nodes: Iterable[TreeNode[LintTreeDataType]]):
+        """Clear and remove the terminals of the finished commands in a set of commands."""
+        tree = self.lint_tree
+        for node in nodes:
+            if node.data is None or node.data.status == "running":
+                continue
+
+            command_id = node.data.id
+            if command_id in self.terminals:
+                self.terminals[command_id].emulator.clear()
+                del self.terminals[command_id]
+                self._group_view.attach(command_id, None)
+            if node.data.status not in (None, "pending"):
+                tree.update_status(command_id, "pending")
+
+        if tree.cursor_node and tree.cursor_node.data:
+            self.display_terminal(tree.cursor_node.data.id)
+
+    def action_show_info(self) -> None:
+        """Show the details of the command under the cursor."""
+        node = self.lint_tree.cursor_node
+        if node and node.data and node.data.command:
+            self.push_screen(CommandInfo(node.data.command))
+
+    def action_toggle_mouse(self) -> None:
+        """Toggle mouse capture, disabling it allows selecting and copying text with the terminal itself."""
+        if self._driver is None:
+            return
+
+        self.mouse_captured = not self.mouse_captured
+        mode = "h" if self.mouse_captured else "l"
+        # Same mouse tracking modes as textual enables on startup
+        self._driver.write("".join(f"\x1b[?{code}{mode}" for code in (1000, 1003, 1015, 1006)))
+        self.notify("Mouse capture enabled" if self.mouse_captured else "Mouse capture disabled, press m to re-enable")
+
+    def action_rerun_failures(self) -> None:
+        """Re-run all failed commands (each in a new terminal)."""
+        nodes = self.lint_tree.command_leafs.values()
+        self._run_commands([node for node in nodes if node.data and node.data.status == "failure"])
+
+    def action_rerun_last(self) -> None:
+        """Select and re-run the last run set of commands."""
+        tree = self.lint_tree
+        nodes = [tree.command_leafs[command_id] for command_id in self.last_run_ids if command_id in tree.command_leafs]
+        for node in nodes:
+            select_node(node)
+        self._run_commands(nodes)
 
     async def _handle_context_menu(
         self, node: TreeNode[LintTreeDataType], event: events.Click, active_node: bool = False
@@ -153,12 +312,9 @@ class FnugApp(App[None]):
         if node.data is None:
             return
 
-        tree = self.lint_tree
-
         def handle_selection(selection: str | None):
             if node.data is None or selection is None:
                 return
-            cursor_id = getattr(tree.cursor_node, "id", None)
 
             if selection == "run":
                 self._run_command(node.data, background=not active_node)
@@ -174,18 +330,24 @@ class FnugApp(App[None]):
                 self._clear_terminal(node.data.id)
             elif selection == "clear":
                 self._clear_terminal(node.data.id)
+            elif selection == "changed-files":
+                self._show_changed_files(node.data)
+            elif selection == "save-output":
+                self._save_output(node.data)
+            elif selection == "copy-command":
+                self._copy_command(node.data)
             elif selection == "run-all":
-                for command in all_commands(node):
-                    if command.data is not None:
-                        self._run_command(command.data, background=cursor_id != command.id)
+                self._run_commands(all_commands(node))
             elif selection == "stop-all":
                 for command in all_commands(node):
                     if command.data is not None:
                         self._stop_command(command.data.id)
             elif selection == "rerun-failures":
-                for command in all_commands(node):
-                    if command.data is not None and command.data.status == "failure":
-                        self._run_command(command.data, background=cursor_id != command.id)
+                self._run_commands(
+                    command for command in all_commands(node) if command.data and command.data.status == "failure"
+                )
+            elif selection == "clear-finished":
+                self._clear_finished(all_commands(node))
             elif selection == "select-all":
                 toggle_select_node(node, True)
             elif selection == "deselect-all":
@@ -205,6 +367,8 @@ class FnugApp(App[None]):
                 commands["deselect-all"] = "Deselect all"
             if sums.failure:
                 commands["rerun-failures"] = "Re-run failures"
+            if sums.success or sums.failure:
+                commands["clear-finished"] = "Clear finished"
 
         elif node.data.status == "running":
             commands = {
@@ -212,18 +376,27 @@ class FnugApp(App[None]):
                 "stop": "Stop",
                 "stop-clear": "Stop and clear",
             }
+            if node.data.id in self.terminals:
+                commands["save-output"] = "Save output"
         elif node.data.status in ("failure", "success"):
             commands = {
                 "run": "Re-run",
                 "run-fullscreen": "Re-run (fullscreen)",
                 "clear": "Clear",
             }
+            if node.data.id in self.terminals:
+                commands["save-output"] = "Save output"
+            if node.data.status == "failure" and node.data.command and node.data.command.auto.git:
+                commands["changed-files"] = "Show changed files"
         else:
             commands = {
                 "run": "Run",
                 "run-fullscreen": "Run (fullscreen)",
             }
 
+        if node.data.command is not None:
+            commands["copy-command"] = "Copy command"
+
         await self.push_screen(
             ContextMenu(
                 commands,
@@ -245,9 +418,6 @@ class FnugApp(App[None]):
 
     def display_terminal(self, command_id: str):
         """Display the terminal for a command."""
-        if self.display_task is not None:
-            self.display_task.cancel()
-
         tree = self.lint_tree
 
         if tree.cursor_node and tree.cursor_node.data and tree.cursor_node.data.id != command_id:
@@ -256,32 +426,135 @@ class FnugApp(App[None]):
                 update_node(new_node)
                 self.lint_tree.select_node(new_node)
 
+        if self.group_view_id is not None:
+            if command_id == self.group_view_id or command_id in self._group_view.terminals:
+                self._group_view.set_active(command_id)
+                return
+            self._close_group_view()
+
+        if self.display_task is not None:
+            self.display_task.cancel()
+
         terminal = self.terminals.get(command_id)
         self.display_task = self.run_worker(
             self._terminal.attach_emulator(terminal.emulator if terminal else None), name="display_task"
         )
 
-    def _run_command(self, command: LintTreeDataType, background: bool = False):
+    async def action_toggle_group_view(self) -> None:
+        """Toggle displaying the terminals of all commands in the highlighted group."""
+        node = self.lint_tree.cursor_node
+        if self.group_view_id is not None:
+            self._close_group_view()
+            if node and node.data:
+                self.display_terminal(node.data.id)
+        elif node and node.data and node.data.type == "group":
+            await self._open_group_view(node)
+
+    async def _open_group_view(self, node: TreeNode[LintTreeDataType]):
+        if node.data is None:
+            return
+
+        if self.display_task is not None:
+            self.display_task.cancel()
+
+        self.group_view_id = node.data.id
+        self._terminal.display = False
+        self._group_view.display = True
+        await self._group_view.show(
+            (command.data.id, command.data.name, getattr(self.terminals.get(command.data.id), "emulator", None))
+            for command in all_commands(node)
+            if command.data
+        )
+
+    def _close_group_view(self):
+        self.group_view_id = None
+        self._group_view.display = False
+        self._terminal.display = True
+        self.run_worker(self._group_view.clear())
+
+    def _run_commands(self, nodes: Iterable[TreeNode[LintTreeDataType]]):
+        """Run a set of commands, and remember them as the last run set."""
+        cursor_id = getattr(self.lint_tree.cursor_node, "id", None)
+
+        run_set: list[str] = []
+        for node in nodes:
+            if node.data is not None:
+                self._run_command(node.data, background=cursor_id != node.id, run_set=run_set)
+
+    def _confirm_run(self, command: LintTreeDataType, run: Callable[[], None], run_set: list[str] | None = None):
+        """
+        Call `run`, after asking for confirmation if the command has `confirm` set.
+
+        Once it's run, the command is added to `run_set` (a new set by default), which becomes the last run set. So
+        declining doesn't change what's re-run.
+        """
+
+        def confirmed():
+            self.last_run_ids = [] if run_set is None else run_set
+            self.last_run_ids.append(command.id)
+            run()
+
+        if command.command is None or not command.command.confirm:
+            confirmed()
+            return
+
+        def handle_answer(answer: bool | None):
+            if answer:
+                confirmed()
+
+        self.push_screen(ConfirmRun(command.command), handle_answer)
+
+    def _run_command(self, command: LintTreeDataType, background: bool = False, run_set: list[str] | None = None):
         if command.type != "command":
             return
+        self._confirm_run(command, partial(self._start_command, command, background), run_set)
 
+    def _start_command(self, command: LintTreeDataType, background: bool = False):
         tree = self.lint_tree
         tree.update_status(command.id, "running")
 
         te = TerminalEmulator(
-            self._terminal.size,
+            self._terminal_size,
             can_focus=command.command.interactive if command.command else False,
+            max_line_length=command.command.max_line_length if command.command else None,
+            tab_width=command.command.tab_width if command.command else 8,
+            scrollback=command.command.scrollback if command.command else 5000,
+            kill_grace=command.command.kill_grace_ms / 1000 if command.command else 3.0,
+            min_size=Size(command.command.min_cols or 0, command.command.min_rows or 0) if command.command else None,
         )
 
         async def run_shell():
+            # the terminal is running until the command and both hooks are done (or it's stopped)
+            try:
+                await run_hooks_and_command()
+            finally:
+                te.finish()
+
+        async def run_hooks_and_command():
+            config_command = command.command
+            if config_command is None:
+                tree.update_status(command.id, "failure")
+                return
+
             cwd = self.cwd
-            if command.command and command.command.cwd:
-                cwd = cwd / command.command.cwd
+            if config_command.cwd:
+                cwd = cwd / config_command.cwd
 
-            if command.command and await te.run_shell(command.command.cmd, cwd):
-                tree.update_status(command.id, "success")
-            else:
-                tree.update_status(command.id, "failure")
+            env = config_command.environment(FORCE_COLOR_ENV if config_command.force_color else None, cwd=cwd)
+            self.event_log.write("start", id=command.id, name=command.name, cmd=config_command.cmd, cwd=cwd)
+            started = time.monotonic()
+
+            def done(success: bool):
+                tree.update_status(command.id, "success" if success else "failure", te.exit_code)
+
+            def after_failed():
+                self.event_log.write("after_failed", id=command.id)
+                self.log.warning(f"The after hook of {command.name} failed")
+
+            success = await te.run_with_hooks(config_command, cwd, env, on_done=done, on_after_failed=after_failed)
+
+            if self.config.notify and time.monotonic() - started >= NOTIFY_AFTER_SECONDS:
+                await self._notify_finished(command.name, success)
 
         if command.id in self.terminals:
             self.terminals[command.id].run_task.cancel()
@@ -290,31 +563,39 @@ class FnugApp(App[None]):
             emulator=te,
             run_task=self.run_worker(run_shell()),
         )
+        self._group_view.attach(command.id, te)
         if not background:
             self.display_terminal(command.id)
+            if te.can_focus and self.group_view_id is None:
+                # focus interactive commands right away, so input goes to them (e.g. a REPL)
+                self._terminal.can_focus = True
+                self._terminal.focus()
+
+    async def _notify_finished(self, name: str, success: bool):
+        """Send a desktop notification about a finished command, falling back to ringing the bell."""
+        if not await send_notification("fnug", f"{name} {'succeeded' if success else 'failed'}"):
+            self.bell()
 
     def _run_command_fullscreen(self, command: LintTreeDataType):
+        self._confirm_run(command, partial(self._start_command_fullscreen, command))
+
+    def _start_command_fullscreen(self, command: LintTreeDataType):
         # stop existing command, if it's running
         self._stop_command(command.id)
 
         if not command.command:
             return
 
+        self.event_log.write("start", id=command.id, name=command.name, cmd=command.command.cmd, fullscreen=True)
         with self.suspend():
             click.clear()
-            rich.print(start_message(command.command.cmd), end="")
-            process = subprocess.run(command.command.cmd, shell=True)  # noqa: S602
-            exit_code = process.returncode
-            if exit_code == 0:
-                rich.print(success_message())
-                status = "success"
-            else:
-                rich.print(failure_message(exit_code))
-                status = "failure"
+            cwd = self.cwd / command.command.cwd if command.command.cwd else self.cwd
+            exit_code = run_foreground(command.command, cwd, command.command.environment(cwd=cwd))
+            status = "success" if exit_code == 0 else "failure"
 
             rich.print(any_key_message())
             click.pause("")
-        self.lint_tree.update_status(command.id, status)
+        self.lint_tree.update_status(command.id, status, exit_code)
 
     def _stop_command(self, command_id: str):
         tree = self.lint_tree
@@ -324,11 +605,55 @@ class FnugApp(App[None]):
             return
 
         if command_id in self.terminals:
+            self.event_log.write("stop", id=command_id)
             self.terminals[command_id].emulator.echo("")  # makes sure the cursor position is reset
             self.terminals[command_id].emulator.echo(stopped_message())
             self.terminals[command_id].run_task.cancel()
             tree.update_status(command_id, "failure")
 
+    def _show_changed_files(self, command: LintTreeDataType):
+        """Show the changed files matching a command's git auto rules, as they likely caused it to fail."""
+        if command.command is None or command.id not in self.terminals:
+            return
+
+        auto = command.command.auto
+        staged, absolute = bool(auto.staged), auto.regex_target == "absolute"
+        try:
+            files = sorted(
+                {
+                    file
+                    for rule in auto.rules()
+                    for file in changed_files(self.cwd / rule.path, rule.regex, auto.git_ref, staged, absolute)
+                }
+            )
+        except (ValueError, GitError) as e:
+            self.notify(str(e), title="Could not get the changed files", severity="error")
+            return
+        self.terminals[command.id].emulator.echo(changed_files_message(files))
+
+    def _copy_command(self, command: LintTreeDataType):
+        """Copy the command (prefixed with a cd to its working directory) to the clipboard."""
+        if command.command is None:
+            return
+
+        cwd = self.cwd / command.command.cwd if command.command.cwd else self.cwd
+        self.copy_to_clipboard(f"cd {shlex.quote(cwd.as_posix())} && {command.command.cmd.strip()}")
+        self.notify(f"Copied {command.name} to the clipboard")
+
+    def _save_output(self, command: LintTreeDataType):
+        """Save the output of a command to a log file in the working directory."""
+        if command.id not in self.terminals:
+            return
+
+        name = re.sub(r"[^\w.-]+", "-", command.name).strip("-")
+        path = self.cwd / f"fnug-{name}-{datetime.now().strftime('%Y%m%d-%H%M%S')}.log"
+        try:
+            path.write_text(self.terminals[command.id].emulator.text())
+        except OSError as e:
+            self.notify(str(e), title="Could not save output", severity="error")
+            return
+        self.notify(f"Saved output to {path}")
+
     def _clear_terminal(self, command_id: str):
         tree = self.lint_tree
 
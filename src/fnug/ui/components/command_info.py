@@ -0,0 +1,68 @@
+from typing import ClassVar
+
+from rich.table import Table
+from textual import events
+from textual.app import ComposeResult
+from textual.binding import Binding, BindingType
+from textual.containers import Vertical
+from textual.screen import ModalScreen
+from textual.widgets import Static
+
+from fnug.config import ConfigCommand
+
+
+def command_info_table(command: ConfigCommand) -> Table:
+    """Create a table describing a command (what it runs, where, and when it's selected)."""
+    table = Table.grid(padding=(0, 2))
+    table.add_column(style="bold #cf6a4c")
+    table.add_column()
+
+    table.add_row("name", command.name)
+    table.add_row("id", command.id)
+    table.add_row("cmd", command.cmd.strip())
+    if command.before:
+        table.add_row("before", command.before.strip())
+    if command.after:
+        table.add_row("after", command.after.strip())
+    table.add_row("cwd", command.cwd.as_posix() if command.cwd else ".")
+    if command.shell:
+        table.add_row("shell", command.shell)
+    for key, value in command.displayed_env().items():
+        table.add_row("env", f"{key}={value}")
+
+    auto = command.auto
+    enabled = [name for name, value in (("git", auto.git), ("watch", auto.watch), ("always", auto.always)) if value]
+    table.add_row("auto", ", ".join(enabled) or "-")
+    for rule in auto.rules():
+        table.add_row("auto path", f"{rule.path.as_posix()} {' '.join(rule.regex or [])}".strip())
+    if auto.when:
+        table.add_row("auto when", auto.when)
+    return table
+
+
+class CommandInfo(ModalScreen[None]):
+    """A popup showing the details of a command, closes on any key or click."""
+
+    CSS_PATH = "command_info.tcss"
+
+    # escape would otherwise quit the app
+    BINDINGS: ClassVar[list[BindingType]] = [Binding("escape", "close", "Close", show=False)]
+
+    def __init__(self, command: ConfigCommand) -> None:
+        self.command = command
+        super().__init__()
+
+    def compose(self) -> ComposeResult:  # noqa: D102
+        with Vertical(id="container"):
+            yield Static(command_info_table(self.command))
+
+    def action_close(self) -> None:
+        """Close the popup."""
+        self.dismiss(None)
+
+    async def _on_key(self, event: events.Key) -> None:
+        event.stop()
+        self.dismiss(None)
+
+    async def _on_click(self, event: events.Click) -> None:
+        self.dismiss(None)
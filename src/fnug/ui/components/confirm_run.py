@@ -0,0 +1,44 @@
+from typing import ClassVar
+
+from textual import on
+from textual.app import ComposeResult
+from textual.binding import Binding, BindingType
+from textual.containers import Horizontal, Vertical
+from textual.screen import ModalScreen
+from textual.widgets import Label
+
+from fnug.config import ConfigCommand
+from fnug.ui.components.context_menu import ContextMenuItem
+
+
+class ConfirmRun(ModalScreen[bool]):
+    """A yes/no popup asking if a command (with `confirm` set) should be run."""
+
+    CSS_PATH = "confirm_run.tcss"
+
+    BINDINGS: ClassVar[list[BindingType]] = [
+        Binding("y", "answer(True)", "Yes", show=False),
+        Binding("n,escape", "answer(False)", "No", show=False),
+    ]
+
+    def __init__(self, command: ConfigCommand) -> None:
+        self.command = command
+        super().__init__()
+
+    def compose(self) -> ComposeResult:  # noqa: D102
+        # markup is disabled, as both the command and the key hints contain brackets
+        with Vertical(id="container"):
+            yield Label(f"Run {self.command.name}?", id="question", markup=False)
+            yield Label(self.command.cmd.strip(), id="cmd", markup=False)
+            with Horizontal(id="answers"):
+                yield ContextMenuItem("[y] Yes", id="yes", classes="options", markup=False)
+                yield ContextMenuItem("[n] No", id="no", classes="options", markup=False)
+
+    def action_answer(self, answer: bool) -> None:
+        """Close the popup with the answer."""
+        self.dismiss(answer)
+
+    @on(ContextMenuItem.Clicked)
+    def _answer_clicked(self, event: ContextMenuItem.Clicked) -> None:
+        event.stop()
+        self.dismiss(event.element.id == "yes")
@@ -0,0 +1,64 @@
+from collections.abc import Iterable
+
+from textual.containers import Vertical
+from textual.widgets import Label
+from textual.worker import Worker
+
+from fnug.terminal_emulator import TerminalEmulator
+from fnug.ui.components.terminal import Terminal
+
+
+class GroupView(Vertical):
+    """Displays the terminals of multiple commands, stacked vertically with a small header."""
+
+    def __init__(
+        self,
+        name: str | None = None,
+        id: str | None = None,
+        classes: str | None = None,
+    ) -> None:
+        self.terminals: dict[str, Terminal] = {}
+        self.headers: dict[str, Label] = {}
+        self.display_tasks: dict[str, Worker[None]] = {}
+        self.active_id: str | None = None
+        super().__init__(name=name, id=id, classes=classes)
+
+    async def show(self, commands: Iterable[tuple[str, str, TerminalEmulator | None]], active_id: str | None = None):
+        """Show the terminals of a list of commands (id, name and emulator)."""
+        await self.clear()
+        self.active_id = active_id
+
+        for command_id, name, emulator in commands:
+            self.headers[command_id] = Label(name, classes="group-view--header")
+            self.terminals[command_id] = Terminal(classes="custom-scrollbar")
+            await self.mount(self.headers[command_id], self.terminals[command_id])
+            self.attach(command_id, emulator)
+
+    async def clear(self):
+        """Remove all terminals."""
+        self.terminals = {}
+        self.headers = {}
+        self.display_tasks = {}
+        await self.remove_children()
+
+    def attach(self, command_id: str, emulator: TerminalEmulator | None):
+        """Attach an emulator to the terminal of a command (if it's displayed)."""
+        terminal = self.terminals.get(command_id)
+        if terminal is None:
+            return
+
+        if command_id in self.display_tasks:
+            self.display_tasks[command_id].cancel()
+        self.display_tasks[command_id] = terminal.run_worker(terminal.attach_emulator(emulator))
+        # attaching an emulator resets whether the terminal can be focused, so the active terminal is re-applied
+        self.call_after_refresh(lambda: self.set_active(self.active_id))
+
+    def set_active(self, command_id: str | None):
+        """Mark the terminal of a command as active, only the active terminal can receive input."""
+        self.active_id = command_id
+        for header_id, header in self.headers.items():
+            header.set_class(header_id == command_id, "-active")
+
+        for terminal_id, terminal in self.terminals.items():
+            emulator = terminal.emulator
+            terminal.can_focus = terminal_id == command_id and emulator is not None and emulator.can_focus
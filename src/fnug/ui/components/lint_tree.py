@@ -1,11 +1,12 @@
-import re
+import asyncio
+import math
 import time
-from collections import defaultdict
 from collections.abc import Iterator
 from dataclasses import dataclass
 from pathlib import Path
 from typing import ClassVar, Literal
 
+from pygit2 import GitError
 from rich.style import Style
 from rich.text import Text
 from textual import events
@@ -18,10 +19,24 @@ from textual.widgets._tree import TOGGLE_STYLE, TreeNode
 from textual.worker import Worker
 from watchfiles import awatch  # pyright: ignore reportUnknownVariableType
 
-from fnug.config import Config, ConfigCommand, ConfigCommandGroup
-from fnug.git import detect_repo_changes
+from fnug.config import Config, ConfigAuto, ConfigCommand, ConfigCommandGroup, ConfigWatchRule, iter_commands
+from fnug.event_log import EventLog
+from fnug.git import StatusCache, detect_repo_changes, is_git_ignored, repo_root
+from fnug.state import TreeState, save_state
+
+# How long changes to the files of a command are collected before it's selected (unless `debounce_ms` is set)
+DEFAULT_DEBOUNCE_MS = 300
+# How often the watcher reports changes, the changes are then debounced per command
+WATCH_STEP_MS = 50
 
 StatusType = Literal["success", "failure", "running", "pending"]
+SortType = Literal["config", "name", "status", "duration"]
+
+# Colors of the status and counts in the labels, for dark and light backgrounds
+LABEL_COLORS: dict[bool, dict[str, str]] = {
+    True: {"muted": "#808080", "success": "green", "failure": "red", "running": "yellow"},
+    False: {"muted": "#6c6c6c", "success": "#2e7d32", "failure": "#c62828", "running": "#b26a00"},
+}
 
 
 @dataclass
@@ -35,6 +50,21 @@ class LintTreeDataType:
     group: ConfigCommandGroup | None = None
     status: StatusType | None = None
     selected: bool = False
+    started: float | None = None
+    finished: float | None = None
+    exit_code: int | None = None
+
+    def elapsed(self) -> float:
+        """The time the command has been running (or ran for), 0 if it hasn't been run."""
+        if self.started is None:
+            return 0
+        return (self.finished or time.monotonic()) - self.started
+
+
+def format_duration(seconds: float) -> str:
+    """Format a duration as seconds (or minutes and seconds), e.g. `12s` or `1m05s`."""
+    minutes, seconds = divmod(int(seconds), 60)
+    return f"{minutes}m{seconds:02}s" if minutes else f"{seconds}s"
 
 
 def update_node(node: TreeNode[LintTreeDataType]):
@@ -80,6 +110,23 @@ def toggle_select_node(node: TreeNode[LintTreeDataType], override_value: bool |
         toggle_select_node(child, override_value=override_value)
 
 
+def all_nodes(source_node: TreeNode[LintTreeDataType]) -> Iterator[TreeNode[LintTreeDataType]]:
+    """Get all children of a node (recursively)."""
+    for child in source_node.children:
+        yield child
+        yield from all_nodes(child)
+
+
+def node_key(node: TreeNode[LintTreeDataType]) -> str:
+    """Get a key identifying a node between runs (the names of the node and its parents)."""
+    names: list[str] = []
+    current: TreeNode[LintTreeDataType] | None = node
+    while current is not None and current.data is not None:
+        names.append(current.data.name)
+        current = current.parent
+    return " > ".join(reversed(names))
+
+
 def all_commands(source_node: TreeNode[LintTreeDataType]) -> Iterator[TreeNode[LintTreeDataType]]:
     """Get all command children of a node (recursively)."""
     for child in source_node.children:
@@ -88,25 +135,29 @@ def all_commands(source_node: TreeNode[LintTreeDataType]) -> Iterator[TreeNode[L
         yield from all_commands(child)
 
 
-def select_git_auto(cwd: Path, node: TreeNode[LintTreeDataType]):
-    """Select nodes if it has git auto enabled and there are changes in the repos."""
+def select_git_auto(cwd: Path, node: TreeNode[LintTreeDataType], status_cache: StatusCache | None = None) -> bool:
+    """
+    Deselect a node, and check if it should be selected (always or git auto, with changes in the repos).
+
+    The `when` guard isn't checked, as it runs a command, which is left to the caller (off the UI thread). The status
+    of the repos is reused from `status_cache`, if given.
+    """
     if not node.data or not node.data.command:
-        return
+        return False
 
     auto = node.data.command.auto
     node.data.selected = False
 
     if auto.always is True:
-        node.data.selected = True
+        return True
 
-    if auto.git and auto.path:
-        for path in auto.path:
-            if detect_repo_changes(cwd / path, auto.regex):
-                node.data.selected = True
-                continue
-
-    if node.data.selected:
-        update_node(node)
+    if auto.git:
+        for rule in auto.rules():
+            absolute = auto.regex_target == "absolute"
+            staged = bool(auto.staged)
+            if detect_repo_changes(cwd / rule.path, rule.regex, auto.git_ref, staged, absolute, status_cache):
+                return True
+    return False
 
 
 @dataclass
@@ -118,6 +169,7 @@ class CommandSum:
     success: int = 0
     failure: int = 0
     total: int = 0
+    elapsed: float = 0
 
 
 def sum_selected_commands(source_node: TreeNode[LintTreeDataType]) -> CommandSum:
@@ -126,6 +178,7 @@ def sum_selected_commands(source_node: TreeNode[LintTreeDataType]) -> CommandSum
     for child in source_node.children:
         if child.data and child.data.type == "command":
             command_sum.total += 1
+            command_sum.elapsed += child.data.elapsed()
             if child.data.selected:
                 command_sum.selected += 1
             if child.data.status == "running":
@@ -141,16 +194,56 @@ def sum_selected_commands(source_node: TreeNode[LintTreeDataType]) -> CommandSum
             command_sum.running += child_sum.running
             command_sum.success += child_sum.success
             command_sum.failure += child_sum.failure
+            command_sum.elapsed += child_sum.elapsed
     return command_sum
 
 
+def _status_rank(node: TreeNode[LintTreeDataType]) -> int:
+    """Rank a node by status (failures first, then running, not run and finally successful)."""
+    if node.data and node.data.type == "command":
+        status = node.data.status
+        return {"failure": 0, "running": 1, "success": 3}.get(status or "pending", 2)
+    command_sum = sum_selected_commands(node)
+    if command_sum.failure:
+        return 0
+    if command_sum.running:
+        return 1
+    return 3 if command_sum.total and command_sum.success == command_sum.total else 2
+
+
+def _last_duration(node: TreeNode[LintTreeDataType]) -> float | None:
+    """The duration of the last run of a command (summed for groups), None if it hasn't been run."""
+    commands = [node] if node.data and node.data.type == "command" else list(all_commands(node))
+    durations = [command.data.elapsed() for command in commands if command.data and command.data.started is not None]
+    return sum(durations) if durations else None
+
+
+def sort_nodes(source_node: TreeNode[LintTreeDataType], sort: SortType):
+    """Sort the children of a node (recursively), nodes with equal keys keep the order of the config."""
+
+    def key(node: TreeNode[LintTreeDataType]) -> tuple[float | str, int]:
+        if sort == "name" and node.data:
+            return node.data.name.lower(), node.id
+        if sort == "status":
+            return _status_rank(node), node.id
+        if sort == "duration":
+            # the slowest first, commands that haven't been run last
+            duration = _last_duration(node)
+            return -duration if duration is not None else math.inf, node.id
+        return 0, node.id
+
+    source_node._children.sort(key=key)  # pyright: ignore reportPrivateUsage=false
+    for child in source_node.children:
+        sort_nodes(child, sort)
+
+
 def attach_command(
     tree: TreeNode[LintTreeDataType],
     command_group: ConfigCommandGroup,
     cwd: Path,
     root: bool = False,
 ) -> dict[str, TreeNode[LintTreeDataType]]:
-    """Attach a command group to a tree."""
+    """Attach a command group to a tree, disabled commands and groups are left out."""
     command_leafs: dict[str, TreeNode[LintTreeDataType]] = {}
 
     if not root:
@@ -161,44 +254,68 @@ def attach_command(
     else:
         new_root = tree
 
-    for command in command_group.commands:
+    for command in command_group.enabled_commands():
         command_leafs[command.id] = new_root.add_leaf(
             command.name,
             data=LintTreeDataType(name=command.name, type="command", command=command, id=command.id),
         )
-    for child in command_group.children:
+    for child in command_group.enabled_children():
+        if next(iter_commands(child), None) is None:
+            # groups where every command is disabled are left out as well
+            continue
         child_commands = attach_command(new_root, child, cwd)
         command_leafs.update(child_commands)
     return command_leafs
 
 
-async def watch_auto_task(command_nodes: Iterator[TreeNode[LintTreeDataType]], cwd: Path):
+async def watch_auto_task(command_nodes: Iterator[TreeNode[LintTreeDataType]], cwd: Path, event_log: EventLog):
     """Create a task that watches for changes in the filesystem and selects auto commands."""
-    paths: defaultdict[Path, list[TreeNode[LintTreeDataType]]] = defaultdict(list)
+    node_rules: list[tuple[TreeNode[LintTreeDataType], ConfigAuto, list[tuple[ConfigWatchRule, Path | None]]]] = []
 
     for node in command_nodes:
-        if not node.data or not node.data.command or not node.data.command.auto.path:
+        if not node.data or not node.data.command or not node.data.command.auto.rules():
             continue
 
-        for path in node.data.command.auto.path:
-            paths[cwd / path].append(node)
+        # regexes are matched against the path relative to the git repository (or the watched path)
+        auto = node.data.command.auto
+        rules = [
+            (rule, None if auto.regex_target == "absolute" else repo_root(cwd / rule.path) or cwd / rule.path)
+            for rule in auto.rules()
+        ]
+        node_rules.append((node, auto, rules))
 
-    async for change_set in awatch(*paths.keys(), step=500, debounce=5000):
-        for _, change_str in change_set:
-            change = Path(change_str)
-            # Only trigger if the path is in the tree
-            nested_active_nodes = [nodes for path, nodes in paths.items() if path in change.parents]
-            active_nodes = [node for nodes in nested_active_nodes for node in nodes]  # Flatten
+    paths = {cwd / rule.path for _, _, rules in node_rules for rule, _ in rules}
+    if not paths:
+        return
 
-            for node in active_nodes:
-                if not node.data or not node.data.command:
+    async def select_after(node: TreeNode[LintTreeDataType], auto: ConfigAuto, path: str):
+        await asyncio.sleep((auto.debounce_ms if auto.debounce_ms is not None else DEFAULT_DEBOUNCE_MS) / 1000)
+        if node.data is None:
+            return
+        if not node.data.selected and not await asyncio.to_thread(auto.when_passes, cwd):
+            return
+        select_node(node)
+        event_log.write("select", id=node.data.id, reason="watch", path=path)
+
+    # Changes are coalesced per command, every matching change restarts the debounce of the command
+    pending: dict[int, asyncio.Task[None]] = {}
+    try:
+        async for change_set in awatch(*paths, step=WATCH_STEP_MS, debounce=WATCH_STEP_MS):
+            for _, change_str in change_set:
+                change = Path(change_str)
+                if is_git_ignored(cwd, change):
                     continue
 
-                if node.data.command.auto.regex:
-                    if any(re.search(r, change_str) for r in node.data.command.auto.regex):
-                        select_node(node)
-                else:
-                    select_node(node)
+                for node, auto, rules in node_rules:
+                    if auto.is_ignored(cwd, change):
+                        continue
+                    if node.data and any(rule.matches(cwd, change, root) for rule, root in rules):
+                        if node.id in pending:
+                            pending[node.id].cancel()
+                        pending[node.id] = asyncio.create_task(select_after(node, auto, change_str))
+    finally:
+        for task in pending.values():
+            task.cancel()
 
 
 class LintTree(Tree[LintTreeDataType]):
@@ -207,6 +324,7 @@ class LintTree(Tree[LintTreeDataType]):
     guide_depth = 3
     show_root = False
     watch_task: Worker[None] | None = None
+    sort: SortType = "config"
     grabbed: Reactive[Offset | None] = Reactive(None)
     last_click: Reactive[dict[int, float | Literal["invalid"]]] = Reactive({})  # used for double click detection
     command_leafs: Reactive[dict[str, TreeNode[LintTreeDataType]]] = Reactive({})
@@ -222,6 +340,8 @@ class LintTree(Tree[LintTreeDataType]):
         Binding("l", "expand_node", "Toggle", show=False),
         Binding("k", "cursor_up", "Cursor Up", show=False),
         Binding("j", "cursor_down", "Cursor Down", show=False),
+        Binding("H", "collapse_all", "Collapse all groups", show=False),
+        Binding("L", "expand_all", "Expand all groups", show=False),
         # Controls
         Binding("r", "run", "Run"),
         Binding("ctrl+r", "exclusive_run", "Run fullscreen", show=False),
@@ -229,8 +349,12 @@ class LintTree(Tree[LintTreeDataType]):
         Binding("space", "toggle_select", "Select"),
         Binding("g", "select_git", "Select based on git changes", show=False),
         Binding("enter", "run_all", "Run selected commands"),
+        Binding("R", "run_group", "Run all commands in group", show=False),
+        Binding("o", "cycle_sort", "Change sort order", show=False),
         Binding("c", "clear", "Clear terminal", show=False),
         Binding("q", "quit", "Quit", show=False),
+        Binding("right_square_bracket", "next_failure", "Next failed command", show=False),
+        Binding("left_square_bracket", "previous_failure", "Previous failed command", show=False),
     ]
 
     class RunCommand(Message):
@@ -304,6 +428,9 @@ class LintTree(Tree[LintTreeDataType]):
         config: Config,
         cwd: Path,
         *,
+        event_log: EventLog | None = None,
+        state: TreeState | None = None,
+        state_path: Path | None = None,
         name: str | None = None,
         id: str | None = None,
         classes: str | None = None,
@@ -312,6 +439,9 @@ class LintTree(Tree[LintTreeDataType]):
         super().__init__("fnug", name=name, id=id, classes=classes, disabled=disabled)
         self.config = config
         self.cwd = cwd
+        self.event_log = event_log or EventLog()
+        self.state = state
+        self.state_path = state_path
 
     def _get_label_region(self, line: int) -> Region | None:
         """Like parent, but offset by 2 to account for the icon."""
@@ -320,13 +450,21 @@ class LintTree(Tree[LintTreeDataType]):
             return None
         return region._replace(x=region.x - 2)
 
-    def update_status(self, command_id: str, status: StatusType):
-        """Update the status of a command."""
+    def update_status(self, command_id: str, status: StatusType, exit_code: int | None = None):
+        """Update the status of a command (and the exit code, if it finished)."""
         node = self.command_leafs[command_id]
         if node.data is None:
             return
 
         node.data.status = status
+        node.data.exit_code = exit_code
+        if status == "running":
+            node.data.started, node.data.finished = time.monotonic(), None
+        elif status == "pending":
+            node.data.started, node.data.finished = None, None
+        else:
+            node.data.finished = time.monotonic()
+        self.event_log.write("status", id=command_id, status=status)
         if status == "success":
             node.data.selected = False
         update_node(node)
@@ -365,6 +503,46 @@ class LintTree(Tree[LintTreeDataType]):
         if len(nodes) > 0:
             self.post_message(self.RunAllCommand(nodes))
 
+    def action_run_group(self) -> None:
+        """Run all commands in the group under the cursor (or the group of the command under the cursor)."""
+        group = self.cursor_node
+        if group and group.data and group.data.type == "command":
+            group = group.parent
+        if group is None:
+            return
+
+        nodes = [node for node in all_commands(group) if node.data and node.data.status != "running"]
+        if len(nodes) > 0:
+            self.post_message(self.RunAllCommand(nodes))
+
+    def action_cycle_sort(self) -> None:
+        """Change the sort order of the tree (config order, by name, status or duration), the config is left as is."""
+        sorts: list[SortType] = ["config", "name", "status", "duration"]
+        self.sort = sorts[(sorts.index(self.sort) + 1) % len(sorts)]
+
+        cursor_node = self.cursor_node
+        sort_nodes(self.root, self.sort)
+        self._invalidate()  # pyright: ignore reportPrivateUsage=false
+        if cursor_node is not None:
+            self.select_node(cursor_node)
+        self.notify(f"Sorted by {self.sort}")
+
+    def action_collapse_all(self) -> None:
+        """Collapse all groups, the cursor is moved to the top level group it was in."""
+        cursor_node = self.cursor_node
+        for child in self.root.children:
+            child.collapse_all()
+
+        while cursor_node is not None and cursor_node.parent is not None and cursor_node.parent != self.root:
+            cursor_node = cursor_node.parent
+        if cursor_node is not None:
+            self.select_node(cursor_node)
+
+    def action_expand_all(self) -> None:
+        """Expand all groups."""
+        for child in self.root.children:
+            child.expand_all()
+
     def action_expand_node(self) -> None:
         """Expand a node (or enable it if it's a command)."""
         if self.cursor_node is None:
@@ -385,6 +563,30 @@ class LintTree(Tree[LintTreeDataType]):
         elif self.cursor_node.children:
             self.cursor_node.collapse()
 
+    def action_next_failure(self) -> None:
+        """Move the cursor to the next failed command."""
+        self._move_to_failure(1)
+
+    def action_previous_failure(self) -> None:
+        """Move the cursor to the previous failed command."""
+        self._move_to_failure(-1)
+
+    def _move_to_failure(self, direction: Literal[1, -1]):
+        """Move the cursor to the next failed command in a direction, wrapping around."""
+        # the tree is walked (instead of the commands in config order), so the displayed order is followed when sorted
+        commands = list(all_commands(self.root))
+        if self.cursor_node in commands:
+            start = commands.index(self.cursor_node)
+        else:
+            start = -1 if direction == 1 else len(commands)
+
+        for offset in range(1, len(commands) + 1):
+            node = commands[(start + direction * offset) % len(commands)]
+            if node.data and node.data.status == "failure":
+                update_node(node)
+                self.select_node(node)
+                return
+
     def action_toggle_select(self) -> None:
         """Toggle a node on click (recursively if with children)."""
         if self.cursor_node is None:
@@ -393,9 +595,40 @@ class LintTree(Tree[LintTreeDataType]):
         toggle_select_node(self.cursor_node)
 
     def action_select_git(self):
-        """Select all git auto commands."""
+        """Select all git auto commands, git errors (e.g. an unknown ref) are shown as notifications."""
+        errors: dict[str, None] = {}
+        guarded: list[TreeNode[LintTreeDataType]] = []
+        # the status of each repo is only read once per selection, so edits are seen on the next one
+        status_cache: StatusCache = {}
         for command in all_commands(self.root):
-            select_git_auto(self.cwd, command)
+            try:
+                matches = select_git_auto(self.cwd, command, status_cache)
+            except (ValueError, GitError) as e:
+                errors[str(e)] = None
+                continue
+            if not matches or command.data is None or command.data.command is None:
+                continue
+            if command.data.command.auto.when is None:
+                select_node(command)
+                self.event_log.write("select", id=command.data.id, reason="git")
+            else:
+                guarded.append(command)
+
+        for error in errors:
+            self.log.error(f"Git selection failed: {error}")
+            self.notify(error, title="Git selection failed", severity="error")
+
+        if guarded:
+            self.run_worker(self._select_when_passes(guarded), group="select-git", exclusive=True)
+
+    async def _select_when_passes(self, nodes: list[TreeNode[LintTreeDataType]]):
+        """Select the git auto commands whose `when` guard passes, the guards run in a thread to not block the UI."""
+        for node in nodes:
+            if node.data is None or node.data.command is None:
+                continue
+            if await asyncio.to_thread(node.data.command.auto.when_passes, self.cwd):
+                select_node(node)
+                self.event_log.write("select", id=node.data.id, reason="git")
 
     def action_toggle_select_click(self, line: int, node: TreeNode[LintTreeDataType] | None = None):
         """Toggle a node on click."""
@@ -438,13 +671,20 @@ class LintTree(Tree[LintTreeDataType]):
             return  # No need to handle double click on groups
 
         last_click = self.last_click.get(line)
-
-        if last_click == "invalid":
+        mouse = self.config.mouse
+
+        if mouse.single_click_run:
+            if last_click == "invalid":
+                self.last_click.pop(line)  # a click on the "selection icon"
+            elif node.data.status != "running":
+                # clicking a running command only shows its output, restarting it is left to the run/stop actions
+                self.post_message(self.RunCommand(node))
+        elif last_click == "invalid":
             # if last "click" was from toggle_select_click, we don't want to handle it as a double click as it's either:
             # 1) already been double-clicked and handled by the rest of the method
             # 2) a single click on the "selection icon", and shouldn't be used to calculate a double click
             self.last_click.pop(line)
-        elif last_click and time.time() - last_click < 0.5:
+        elif last_click and time.time() - last_click < mouse.double_click_ms / 1000:
             self.action_run()
             self.last_click.pop(line, None)
         else:
@@ -455,12 +695,15 @@ class LintTree(Tree[LintTreeDataType]):
         node_label = node._label.copy()  # pyright: ignore reportPrivateUsage=false
         node_label.stylize(style)
 
+        colors = LABEL_COLORS[self.app.dark]
+        success_style = base_style + Style(color=colors["success"])
+        failure_style = base_style + Style(color=colors["failure"])
         group_count = ("", base_style)
         dropdown = ("", base_style)
 
         if node._allow_expand:  # pyright: ignore reportPrivateUsage=false
             command_sum = sum_selected_commands(node)
-            count_style = base_style + Style(color="#808080")
+            count_style = base_style + Style(color=colors["muted"])
 
             group_count_pieces = [
                 Text(" (", count_style),
@@ -474,7 +717,7 @@ class LintTree(Tree[LintTreeDataType]):
                 status_count_pieces = [Text(" [", count_style)]
 
                 if command_sum.success:
-                    status_count_pieces.append(Text(str(command_sum.success), base_style + Style(color="green")))
+                    status_count_pieces.append(Text(str(command_sum.success), success_style))
                     if command_sum.running or command_sum.failure:
                         status_count_pieces.append(Text("|", count_style))
 
@@ -484,7 +727,7 @@ class LintTree(Tree[LintTreeDataType]):
                         status_count_pieces.append(Text("|", count_style))
 
                 if command_sum.failure:
-                    status_count_pieces.append(Text(str(command_sum.failure), base_style + Style(color="red")))
+                    status_count_pieces.append(Text(str(command_sum.failure), failure_style))
 
                 status_count_pieces.append(Text("]", count_style))
 
@@ -493,16 +736,24 @@ class LintTree(Tree[LintTreeDataType]):
                     *group_count_pieces,
                 ]
 
+            if command_sum.elapsed >= 1:
+                group_count_pieces.append(Text(f" {format_duration(command_sum.elapsed)}", count_style))
+
             group_count = Text.assemble(*group_count_pieces)
             dropdown = ("▼ ", base_style + TOGGLE_STYLE) if node.is_expanded else ("▶ ", base_style + TOGGLE_STYLE)
 
         command_status = getattr(node.data, "status", "")
         if command_status == "success":
-            status = (" ✔ ", base_style + Style(color="green"))
+            status = (" ✔ ", success_style)
+        elif command_status == "failure" and self.config.show_exit_code and node.data and node.data.exit_code:
+            status = Text.assemble(
+                (" ✘", failure_style),
+                (f" (exit {node.data.exit_code}) ", base_style + Style(color=colors["muted"])),
+            )
         elif command_status == "failure":
-            status = (" ✘ ", base_style + Style(color="red"))
+            status = (" ✘ ", failure_style)
         elif command_status == "running":
-            status = (" 🕑", base_style + Style(color="yellow"))
+            status = (" 🕑", base_style + Style(color=colors["running"]))
         else:
             status = ("", base_style)
 
@@ -523,13 +774,58 @@ class LintTree(Tree[LintTreeDataType]):
 
         return Text.assemble(dropdown, selection, node_label, status, group_count)
 
+    def get_state(self) -> TreeState:
+        """Get the expanded groups and selected commands."""
+        state = TreeState()
+        for node in all_nodes(self.root):
+            if node.data is None:
+                continue
+            if node.data.type == "group" and node.is_expanded:
+                state.expanded.append(node_key(node))
+            elif node.data.type == "command" and node.data.selected:
+                state.selected.append(node_key(node))
+        return state
+
+    def restore_state(self, state: TreeState):
+        """Restore the expanded groups and selected commands (in addition to the auto selected commands)."""
+        for node in all_nodes(self.root):
+            if node.data is None:
+                continue
+            if node.data.type == "command":
+                node.data.selected = node.data.selected or node_key(node) in state.selected
+            elif node_key(node) in state.expanded:
+                node.expand()
+            else:
+                node.collapse()
+        self.refresh()
+
     def _setup(self):
         self.command_leafs = attach_command(self.root, self.config, self.cwd, root=True)
         self.action_select_git()
-        self.watch_task = self.run_worker(watch_auto_task(all_commands(self.root), self.cwd))
+        if self.state is not None:
+            self.restore_state(self.state)
+        self.watch_task = self.run_worker(watch_auto_task(all_commands(self.root), self.cwd, self.event_log))
 
     def _on_mount(self, event: events.Mount):
         self.call_after_refresh(self._setup)
+        # the label colors depend on the theme, so the cached lines are re-rendered
+        invalidate = self._invalidate  # pyright: ignore reportPrivateUsage=false
+        self.watch(self.app, "dark", lambda _: invalidate(), init=False)
+        self.set_interval(1, self._refresh_running_groups)
+
+    def _refresh_running_groups(self):
+        """Refresh groups with running commands, to update their elapsed time."""
+        for node in all_nodes(self.root):
+            if node.data and node.data.type == "group" and sum_selected_commands(node).running:
+                node.refresh()
+
+    def _on_unmount(self, event: events.Unmount):
+        if self.state_path is not None and self.command_leafs:
+            try:
+                save_state(self.state_path, self.get_state())
+            except OSError as e:
+                # e.g. an unwritable config directory, which shouldn't prevent quitting
+                self.log.warning(f"Could not save the state of the tree: {e}")
 
     async def _on_mouse_down(self, event: events.MouseDown) -> None:
         # We don't want mouse events on the scrollbar bubbling
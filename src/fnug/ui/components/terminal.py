@@ -50,15 +50,26 @@ CTRL_KEYS: dict[str, str] = {
 }
 
 
+# How many columns a shift+scroll moves the terminal horizontally
+HORIZONTAL_SCROLL_STEP = 4
+
+
 class TerminalDisplay(ConsoleRenderable):
     """Rich display for the terminal."""
 
-    def __init__(self, lines: list[Text]):
+    def __init__(self, lines: list[Text], offset_x: int = 0):
         self.lines: list[Text] = lines
+        self.offset_x = offset_x
 
     def __rich_console__(self, console: Console, options: ConsoleOptions) -> RenderResult:
-        """Render the terminal display."""
-        yield from self.lines
+        """Render the terminal display, lines wider than the widget (see `min_cols`) are scrolled by `offset_x`."""
+        for line in self.lines:
+            if self.offset_x:
+                line = line[self.offset_x :]
+            if line.cell_len > options.max_width:
+                line = line.copy()
+                line.truncate(options.max_width)
+            yield line
 
 
 class Terminal(Widget, can_focus=False):
@@ -66,6 +77,8 @@ class Terminal(Widget, can_focus=False):
 
     emulator: TerminalEmulator | None = None
     show_vertical_scrollbar = reactive(True)
+    # how many columns the output is scrolled to the right, if it's wider than the widget
+    offset_x: int = 0
 
     BINDINGS: ClassVar[list[BindingType]] = [
         Binding("shift+tab", "unfocus", "Switch focus"),
@@ -143,6 +156,11 @@ class Terminal(Widget, can_focus=False):
         """Attach a terminal emulator to this widget."""
         self.emulator = emulator
         self.can_focus = emulator.can_focus if emulator else False
+        self.offset_x = 0
+
+        # the emulator might have been displayed in a terminal of a different size
+        if emulator and self.size.width > 0 and self.size.height > 0 and emulator.dimensions != self.size:
+            emulator.dimensions = self.size
 
         self.update_scrollbar()
         self.clear()
@@ -152,7 +170,7 @@ class Terminal(Widget, can_focus=False):
 
         try:
             async for screen in emulator.render():
-                self.terminal_display = TerminalDisplay(screen)
+                self.terminal_display = TerminalDisplay(screen, self.offset_x)
                 self.update_scrollbar()
                 self.refresh()
         except asyncio.CancelledError:
@@ -171,12 +189,26 @@ class Terminal(Widget, can_focus=False):
         if char:
             self.emulator.write(char.encode())
 
+    def scroll_horizontal(self, columns: int):
+        """Scroll the output to the right (or left, if negative), only if it's wider than the widget."""
+        if self.emulator is None:
+            return
+
+        max_offset = max(0, self.emulator.dimensions.width - self.size.width)
+        self.offset_x = min(max(0, self.offset_x + columns), max_offset)
+        self.terminal_display = TerminalDisplay(self.terminal_display.lines, self.offset_x)
+        self.refresh()
+
     def _on_mouse_scroll_down(self, event: events.MouseScrollDown) -> None:
-        if self.emulator:
+        if event.shift:
+            self.scroll_horizontal(HORIZONTAL_SCROLL_STEP)
+        elif self.emulator:
             self.emulator.scroll("down")
 
     def _on_mouse_scroll_up(self, event: events.MouseScrollUp) -> None:
-        if self.emulator:
+        if event.shift:
+            self.scroll_horizontal(-HORIZONTAL_SCROLL_STEP)
+        elif self.emulator:
             self.emulator.scroll("up")
 
     async def _on_click(self, event: events.Click):
@@ -186,7 +218,7 @@ class Terminal(Widget, can_focus=False):
         if event.button in [2, 3]:
             self.post_message(self.OpenContextMenu(self, event))
         else:
-            self.emulator.click(event.x + 1, event.y + 1)
+            self.emulator.click(event.x + self.offset_x + 1, event.y + 1)
 
     @property
     def size(self) -> Size:
@@ -202,6 +234,8 @@ class Terminal(Widget, can_focus=False):
     async def _on_resize(self, event: events.Resize | None = None):
         if self.emulator:
             self.emulator.dimensions = self.size
+        # the output might fit now, or be less wide than the current offset
+        self.scroll_horizontal(0)
         self.update_scrollbar()
 
     @on(ScrollDown)
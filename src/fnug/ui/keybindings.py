@@ -0,0 +1,48 @@
+from collections.abc import Collection, Iterable, Mapping
+from dataclasses import replace
+
+from textual.dom import DOMNode
+
+
+def _configured_keys(keys: str) -> list[str]:
+    return [key.strip() for key in keys.split(",")]
+
+
+def binding_conflicts(nodes: Iterable[DOMNode], keybindings: Mapping[str, str]) -> dict[str, str]:
+    """
+    Find configured keys that are already bound to another action by default (that isn't rebound itself).
+
+    Returns a description of the conflict for each key, e.g. `q is bound to both run and quit`.
+    """
+    defaults: dict[str, str] = {}
+    for node in nodes:
+        for key, binding in node._bindings.keys.items():  # pyright: ignore reportPrivateUsage=false
+            if binding.action not in keybindings:
+                defaults.setdefault(key, binding.action)
+
+    conflicts: dict[str, str] = {}
+    for action, keys in keybindings.items():
+        for key in _configured_keys(keys):
+            if key in defaults and defaults[key] != action:
+                conflicts[key] = f"{key} is bound to both {action} and {defaults[key]}"
+    return conflicts
+
+
+def rebind(node: DOMNode, keybindings: Mapping[str, str], skip: Collection[str] = ()) -> set[str]:
+    """
+    Bind the actions of a node to the configured keys, instead of their default keys.
+
+    Keys are comma separated (e.g. `j,down`), keys in `skip` (e.g. conflicts) aren't bound. Returns the actions that
+    were bound.
+    """
+    bindings = node._bindings.keys  # pyright: ignore reportPrivateUsage=false
+    matched = [(key, binding) for key, binding in bindings.items() if binding.action in keybindings]
+
+    for key, _ in matched:
+        del bindings[key]
+    for _, binding in matched:
+        for key in _configured_keys(keybindings[binding.action]):
+            if key not in skip:
+                bindings[key] = replace(binding, key=key)
+
+    return {binding.action for _, binding in matched}
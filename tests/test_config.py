@@ -0,0 +1,968 @@
+from pathlib import Path
+
+import pytest
+from pydantic import ValidationError
+
+from fnug.config import (
+    Config,
+    ConfigAuto,
+    ConfigCommand,
+    ConfigFileError,
+    ConfigWatchRule,
+    config_schema,
+    duplicate_commands,
+    find_command,
+    format_config,
+    format_validation_error,
+    iter_commands,
+    load_config,
+    load_config_dir,
+    read_env_file,
+    regex_matches,
+    validate_config,
+)
+
+
+def _write_config(tmp_path: Path, content: str) -> Path:
+    path = tmp_path / ".fnug.yaml"
+    path.write_text(content)
+    return path
+
+
+class TestGenerate:
+    def test_commands_are_appended(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: static
+    cmd: echo static
+generate: "echo '[{\\"name\\": \\"generated\\", \\"cmd\\": \\"echo generated\\"}]'"
+""",
+        )
+
+        config = load_config(path)
+
+        assert [command.name for command in config.commands] == ["static", "generated"]
+
+    def test_runs_in_config_directory(self, tmp_path: Path):
+        (tmp_path / "tasks.json").write_text('[{"name": "from-file", "cmd": "true"}]')
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ngenerate: cat tasks.json\n")
+
+        config = load_config(path)
+
+        assert [command.name for command in config.commands] == ["from-file"]
+
+    def test_failing_generator(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ngenerate: exit 3\n")
+
+        with pytest.raises(ValidationError, match="failed with exit code 3"):
+            load_config(path)
+
+    def test_invalid_json(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ngenerate: echo nope\n")
+
+        with pytest.raises(ValidationError, match="did not output valid JSON"):
+            load_config(path)
+
+
+class TestInclude:
+    def test_included_files_are_children(self, tmp_path: Path):
+        (tmp_path / "backend").mkdir()
+        (tmp_path / "backend" / ".fnug.yaml").write_text(
+            "name: backend\ncommands:\n  - name: test\n    cmd: 'true'\ninclude: [nested.yaml]\n"
+        )
+        (tmp_path / "backend" / "nested.yaml").write_text("name: nested\ncommands:\n  - name: lint\n    cmd: 'true'\n")
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ninclude: [backend/.fnug.yaml]\n")
+
+        config = load_config(path)
+
+        assert [child.name for child in config.children] == ["backend"]
+        assert [child.name for child in config.children[0].children] == ["nested"]
+
+    def test_duplicate_ids_across_files(self, tmp_path: Path):
+        (tmp_path / "other.yaml").write_text("name: other\ncommands:\n  - id: same\n    name: b\n    cmd: 'true'\n")
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - id: same
+    name: a
+    cmd: "true"
+include: [other.yaml]
+""",
+        )
+
+        assert validate_config(load_config(path), tmp_path) == ["root > other > b: duplicate id 'same'"]
+
+    def test_cycle(self, tmp_path: Path):
+        (tmp_path / "other.yaml").write_text("name: other\ninclude: [.fnug.yaml]\n")
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ninclude: [other.yaml]\n")
+
+        with pytest.raises(ValidationError, match="Include cycle detected"):
+            load_config(path)
+
+    def test_missing_file(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ninclude: [missing.yaml]\n")
+
+        with pytest.raises(ValidationError, match="Included file missing.yaml does not exist"):
+            load_config(path)
+
+
+class TestLoadConfigDir:
+    def test_files_are_children(self, tmp_path: Path):
+        (tmp_path / ".fnug.lint.yaml").write_text(
+            "fnug_version: 0.1.0\nname: lint\ncommands: [{name: ruff, cmd: ruff check}]\n"
+        )
+        (tmp_path / ".fnug.test.json").write_text(
+            '{"fnug_version": "0.1.0", "name": "test", "commands": [{"name": "pytest", "cmd": "pytest"}]}'
+        )
+        (tmp_path / ".fnug.txt").write_text("not a config")
+
+        config = load_config_dir(tmp_path)
+
+        assert config.name == tmp_path.name
+        assert [child.name for child in config.children] == ["lint", "test"]
+
+    def test_no_config_files(self, tmp_path: Path):
+        with pytest.raises(ValueError, match="Could not find any config files"):
+            load_config_dir(tmp_path)
+
+    def test_escaped_dollar_is_expanded_once(self, tmp_path: Path, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FOO", "expanded")
+        (tmp_path / ".fnug.yaml").write_text(
+            "fnug_version: 0.1.0\nname: lint\ncommands: [{name: a, cmd: echo, cwd: 'x$$FOO'}]\n"
+        )
+
+        assert load_config_dir(tmp_path).children[0].commands[0].cwd == Path("x$FOO")
+
+    def test_env_file_values_are_masked(self, tmp_path: Path):
+        (tmp_path / ".env").write_text("SECRET=hunter2\n")
+        (tmp_path / ".fnug.yaml").write_text(
+            "fnug_version: 0.1.0\nname: lint\ncommands: [{name: a, cmd: echo, env_file: [.env]}]\n"
+        )
+
+        command = load_config_dir(tmp_path).children[0].commands[0]
+
+        assert command.env == {"SECRET": "hunter2"}
+        assert command.displayed_env() == {"SECRET": "***"}
+
+    def test_root_settings_of_files(self, tmp_path: Path):
+        (tmp_path / ".fnug.yaml").write_text(
+            "fnug_version: 0.1.0\nname: lint\nnotify: true\ncommands: [{name: a, cmd: echo}]\n"
+        )
+
+        assert [command.name for command in iter_commands(load_config_dir(tmp_path))] == ["a"]
+
+    def test_errors_point_to_the_file(self, tmp_path: Path):
+        (tmp_path / ".fnug.a.yaml").write_text("fnug_version: 0.1.0\nname: a\ncommands: [{name: a, cmd: echo}]\n")
+        path = tmp_path / ".fnug.b.yaml"
+        path.write_text("fnug_version: 0.1.0\nname: b\ncommands:\n  - name: b\n    scrollback: many\n")
+
+        with pytest.raises(ConfigFileError) as error:
+            load_config_dir(tmp_path)
+
+        assert error.value.path == path
+        assert str(error.value).splitlines()[0] == f"{path}:4:5: commands.0.cmd: Field required"
+
+
+class TestRegexMatches:
+    def test_inclusions(self):
+        assert regex_matches([r"\.rs$", r"\.toml$"], "src/main.rs")
+        assert not regex_matches([r"\.rs$", r"\.toml$"], "README.md")
+
+    def test_exclusion(self):
+        regex = [r".*\.rs$", "!.*/generated/.*"]
+
+        assert regex_matches(regex, "src/main.rs")
+        assert not regex_matches(regex, "src/generated/schema.rs")
+
+    def test_order(self):
+        assert not regex_matches([r"\.rs$", "!^generated/"], "generated/schema.rs")
+        assert regex_matches(["!^generated/", r"\.rs$"], "generated/schema.rs")
+
+    def test_only_exclusions(self):
+        assert regex_matches(["!^generated/"], "src/main.rs")
+        assert not regex_matches(["!^generated/"], "generated/schema.rs")
+
+    def test_invalid_exclusion(self):
+        with pytest.raises(ValidationError, match="Invalid regex"):
+            ConfigAuto(regex=["!("])
+
+
+class TestRegexValidation:
+    def test_all_invalid_patterns_are_reported(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: first
+    cmd: "true"
+    auto:
+      regex: ["(", "valid"]
+  - name: second
+    cmd: "true"
+    auto:
+      regex: ["[", "*"]
+""",
+        )
+
+        with pytest.raises(ValidationError) as exc_info:
+            load_config(path)
+
+        message = str(exc_info.value)
+        assert "2 validation errors" in message
+        assert "'('" in message
+        assert "'['" in message
+        assert "'*'" in message
+
+
+class TestValidateConfig:
+    def test_valid(self, tmp_path: Path):
+        (tmp_path / "src").mkdir()
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: lint
+    cmd: "true"
+    cwd: src
+    auto:
+      git: true
+      path: ["src"]
+""",
+        )
+
+        assert validate_config(load_config(path), tmp_path) == []
+
+    def test_problems(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - id: same
+    name: first
+    cmd: "true"
+    cwd: missing
+  - id: same
+    name: second
+    cmd: "true"
+    auto:
+      watch: true
+      path: ["missing"]
+""",
+        )
+
+        assert validate_config(load_config(path), tmp_path) == [
+            "root > first: cwd missing is not a directory",
+            "root > second: duplicate id 'same'",
+            "root > second: auto path missing does not exist",
+        ]
+
+
+class TestWatchRules:
+    def test_flat_fields_are_rules(self):
+        auto = ConfigAuto(path=[Path("src"), Path("docs")], regex=[r"\.py$"])
+
+        assert [(rule.path, rule.regex) for rule in auto.rules()] == [
+            (Path("src"), [r"\.py$"]),
+            (Path("docs"), [r"\.py$"]),
+        ]
+
+    def test_rules_are_combined(self):
+        auto = ConfigAuto(
+            path=[Path("src")],
+            watch_rules=[ConfigWatchRule(path=Path("docs"), regex=[r"\.md$"])],
+        )
+
+        assert [rule.path for rule in auto.rules()] == [Path("src"), Path("docs")]
+
+    def test_rule_matching(self):
+        cwd = Path("/project")
+        auto = ConfigAuto(
+            watch=True,
+            watch_rules=[
+                ConfigWatchRule(path=Path("src"), regex=[r"\.rs$"]),
+                ConfigWatchRule(path=Path("docs"), regex=[r"\.md$"]),
+            ],
+        )
+
+        def matches(file: str) -> bool:
+            return any(rule.matches(cwd, Path(file)) for rule in auto.rules())
+
+        assert matches("/project/src/main.rs")
+        assert matches("/project/docs/index.md")
+        assert not matches("/project/src/index.md")
+        assert not matches("/project/docs/main.rs")
+        assert not matches("/other/src/main.rs")
+
+    def test_regex_relative_to_root(self):
+        cwd = Path("/project")
+        rule = ConfigWatchRule(path=Path("src"), regex=[r"^src/.*\.rs$"])
+
+        assert rule.matches(cwd, Path("/project/src/main.rs"), root=cwd)
+        assert not rule.matches(cwd, Path("/project/src/main.rs"))
+
+    def test_regex_target_is_inherited(self):
+        auto = ConfigAuto(regex_target="absolute")
+
+        assert ConfigAuto().merge(auto).regex_target == "absolute"
+
+    def test_watch_requires_path_or_rules(self):
+        with pytest.raises(ValidationError, match="watch auto requires path or watch_rules"):
+            ConfigAuto(watch=True)
+
+
+class TestEnvironment:
+    def test_inherits_environment(self, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_TEST", "inherited")
+        command = ConfigCommand(name="test", cmd="true", env={"OWN": "1"})
+
+        env = command.environment()
+
+        assert env["FNUG_TEST"] == "inherited"
+        assert env["OWN"] == "1"
+
+    def test_env_takes_precedence(self, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_TEST", "inherited")
+        command = ConfigCommand(name="test", cmd="true", env={"FNUG_TEST": "own", "FORCE_COLOR": "0"})
+
+        env = command.environment({"FORCE_COLOR": "1"})
+
+        assert env["FNUG_TEST"] == "own"
+        assert env["FORCE_COLOR"] == "0"
+
+    def test_env_clear(self, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_TEST", "inherited")
+        command = ConfigCommand(name="test", cmd="true", env_clear=True, env={"OWN": "1"})
+
+        assert command.environment({"FORCE_COLOR": "1"}) == {"FORCE_COLOR": "1", "OWN": "1"}
+
+    def test_env_passthrough(self, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_TEST", "inherited")
+        monkeypatch.setenv("FNUG_OTHER", "other")
+        command = ConfigCommand(name="test", cmd="true", env_passthrough=["FNUG_TEST", "FNUG_MISSING"])
+
+        assert command.environment() == {"FNUG_TEST": "inherited"}
+
+    def test_path_prepend(self, monkeypatch: pytest.MonkeyPatch, tmp_path: Path):
+        monkeypatch.setenv("PATH", "/usr/bin")
+        command = ConfigCommand(name="test", cmd="true", path_prepend=[Path("node_modules/.bin"), Path("/opt/bin")])
+
+        env = command.environment(cwd=tmp_path)
+
+        assert env["PATH"] == f"{tmp_path.as_posix()}/node_modules/.bin:/opt/bin:/usr/bin"
+
+    def test_path_prepend_is_inherited(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+path_prepend: [root]
+children:
+  - name: child
+    path_prepend: [child]
+    commands:
+      - name: test
+        cmd: "true"
+        path_prepend: [command]
+""",
+        )
+
+        config = load_config(path)
+
+        assert config.children[0].commands[0].path_prepend == [Path("command"), Path("child"), Path("root")]
+
+
+class TestInterpolateEnv:
+    def test_expands_variables(self, tmp_path: Path, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_ROOT", "/project")
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: test
+    cmd: $FNUG_ROOT/bin/foo ${FNUG_ROOT}
+    cwd: ${FNUG_ROOT}/backend
+    auto: {watch: true, path: [$FNUG_ROOT/src]}
+""",
+        )
+
+        command = load_config(path).commands[0]
+
+        assert command.cwd == Path("/project/backend")
+        assert command.auto.path == [Path("/project/src")]
+        # the cmd is expanded by the shell
+        assert command.cmd == "$FNUG_ROOT/bin/foo ${FNUG_ROOT}"
+
+    def test_shell_variables_in_cmd(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a, cmd: 'for f in *.py; do echo $f; done'}]\n"
+        )
+
+        assert load_config(path).commands[0].cmd == "for f in *.py; do echo $f; done"
+
+    def test_env_takes_precedence(self, tmp_path: Path, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.setenv("FNUG_TEST", "inherited")
+        command = "{name: a, cmd: b, cwd: $FNUG_TEST, env: {FNUG_TEST: own}}"
+        path = _write_config(tmp_path, f"fnug_version: 0.1.0\nname: root\ncommands: [{command}]\n")
+
+        assert load_config(path).commands[0].cwd == Path("own")
+
+    def test_group_env(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+env: {FNUG_DIR: backend}
+commands:
+  - {name: a, cmd: b, cwd: $FNUG_DIR, auto: {watch: true, path: [$FNUG_DIR/src]}}
+""",
+        )
+
+        command = load_config(path).commands[0]
+
+        assert command.cwd == Path("backend")
+        assert command.auto.path == [Path("backend/src")]
+
+    def test_escaped_dollar(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a, cmd: b, cwd: a$$b}]\n")
+
+        assert load_config(path).commands[0].cwd == Path("a$b")
+
+    def test_unset_variable(self, tmp_path: Path, monkeypatch: pytest.MonkeyPatch):
+        monkeypatch.delenv("FNUG_MISSING", raising=False)
+        path = _write_config(
+            tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: test, cmd: b, cwd: $FNUG_MISSING}]\n"
+        )
+
+        with pytest.raises(ValidationError, match="Command 'test' uses environment variable FNUG_MISSING"):
+            load_config(path)
+
+
+class TestAutoPathGlob:
+    def test_expands_pattern(self, tmp_path: Path):
+        for package in ["a", "b"]:
+            (tmp_path / "packages" / package / "src").mkdir(parents=True)
+        (tmp_path / "packages" / "c").mkdir()
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: test
+    cmd: "true"
+    auto:
+      watch: true
+      path: ["packages/*/src", "docs"]
+""",
+        )
+
+        config = load_config(path)
+
+        assert config.commands[0].auto.path == [Path("packages/a/src"), Path("packages/b/src"), Path("docs")]
+
+    def test_expands_watch_rules(self, tmp_path: Path):
+        for package in ["a", "b"]:
+            (tmp_path / "packages" / package).mkdir(parents=True)
+        auto = ConfigAuto(watch=True, watch_rules=[ConfigWatchRule(path=Path("packages/*"), regex=[r"\.py$"])])
+
+        auto.expand_paths({}, tmp_path)
+
+        assert [(rule.path, rule.regex) for rule in auto.rules()] == [
+            (Path("packages/a"), [r"\.py$"]),
+            (Path("packages/b"), [r"\.py$"]),
+        ]
+
+    def test_no_matches(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+auto:
+  watch: true
+  path: ["packages/*/src"]
+commands:
+  - name: test
+    cmd: "true"
+""",
+        )
+
+        with pytest.raises(ValidationError, match="Auto path pattern packages/\\*/src does not match"):
+            load_config(path)
+
+
+class TestConfigSchema:
+    def test_describes_config(self):
+        schema = config_schema()
+
+        assert schema["title"] == "Config"
+        assert "fnug_version" in schema["required"]
+        assert {"ConfigCommand", "ConfigCommandGroup", "ConfigAuto"} <= set(schema["$defs"])
+
+
+class TestShell:
+    def test_default_shell(self):
+        assert ConfigCommand(name="test", cmd="echo hi").shell_args() is None
+
+    def test_shell_args(self):
+        command = ConfigCommand(name="test", cmd="echo hi", shell="bash -eo pipefail")
+
+        assert command.shell_args() == ["bash", "-eo", "pipefail", "-c", "echo hi"]
+        assert command.shell_args("echo hook") == ["bash", "-eo", "pipefail", "-c", "echo hook"]
+
+    def test_inherited_from_group(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+shell: bash
+children:
+  - name: group
+    commands:
+      - name: inherited
+        cmd: "true"
+      - name: own
+        cmd: "true"
+        shell: fish
+""",
+        )
+
+        config = load_config(path)
+
+        assert [command.shell for command in config.children[0].commands] == ["bash", "fish"]
+
+
+class TestKeybindings:
+    def test_keybindings(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+keybindings:
+  run: x
+  cursor_down: "n, down"
+commands:
+  - name: test
+    cmd: "true"
+""",
+        )
+
+        assert load_config(path).keybindings == {"run": "x", "cursor_down": "n, down"}
+
+    def test_conflicting_keys(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+keybindings:
+  run: x
+  stop: "s,x"
+commands:
+  - name: test
+    cmd: "true"
+""",
+        )
+
+        with pytest.raises(ValidationError, match="x is bound to both run and stop"):
+            load_config(path)
+
+
+class TestAutoIgnore:
+    def test_ignore_patterns(self, tmp_path: Path):
+        auto = ConfigAuto(watch=True, path=[Path("src")], ignore=["target/", "*.log", "build/out"])
+
+        assert auto.is_ignored(tmp_path, tmp_path / "src" / "target" / "debug" / "main.o")
+        assert auto.is_ignored(tmp_path, tmp_path / "src" / "nested" / "test.log")
+        assert auto.is_ignored(tmp_path, tmp_path / "src" / "build" / "out" / "index.js")
+        assert not auto.is_ignored(tmp_path, tmp_path / "src" / "main.rs")
+        assert not auto.is_ignored(tmp_path, tmp_path / "src" / "nested" / "build" / "out" / "index.js")
+
+    def test_relative_to_rule_path(self, tmp_path: Path):
+        auto = ConfigAuto(watch=True, path=[Path("src")], ignore=["src"])
+
+        assert not auto.is_ignored(tmp_path, tmp_path / "src" / "main.rs")
+        assert not auto.is_ignored(tmp_path, tmp_path / "other" / "src" / "main.rs")
+
+    def test_inherited(self):
+        auto = ConfigAuto(watch=True, path=[Path("src")]).merge(ConfigAuto(ignore=["*.log"]))
+
+        assert auto.ignore == ["*.log"]
+
+
+class TestAutoWhen:
+    def test_no_guard(self, tmp_path: Path):
+        assert ConfigAuto(always=True).when_passes(tmp_path)
+
+    def test_guard(self, tmp_path: Path):
+        (tmp_path / "Cargo.toml").write_text("")
+
+        assert ConfigAuto(always=True, when="test -f Cargo.toml").when_passes(tmp_path)
+        assert not ConfigAuto(always=True, when="test -f package.json").when_passes(tmp_path)
+
+
+class TestRequires:
+    def _config(self, tmp_path: Path, command: str, group: str = "") -> Path:
+        return _write_config(tmp_path, f"fnug_version: 0.1.0\nname: root\n{group}commands: [{command}]\n")
+
+    def test_installed(self, tmp_path: Path):
+        path = self._config(tmp_path, "{name: test, cmd: echo, requires: [sh]}")
+
+        assert load_config(path).commands[0].requires == ["sh"]
+
+    def test_missing(self, tmp_path: Path):
+        path = self._config(tmp_path, "{name: test, cmd: echo, requires: [sh, fnug-missing-binary]}")
+
+        with pytest.raises(ValidationError, match="Command 'test' requires fnug-missing-binary, which could not"):
+            load_config(path)
+
+    def test_command_path(self, tmp_path: Path):
+        executable = tmp_path / "fnug-test-binary"
+        executable.write_text("#!/bin/sh\n")
+        executable.chmod(0o755)
+        command = f"{{name: test, cmd: echo, env: {{PATH: {tmp_path}}}, requires: [fnug-test-binary]}}"
+        path = self._config(tmp_path, command)
+
+        assert load_config(path).commands[0].requires == ["fnug-test-binary"]
+
+    def test_group_path_prepend(self, tmp_path: Path):
+        executable = tmp_path / "bin" / "fnug-test-binary"
+        executable.parent.mkdir()
+        executable.write_text("#!/bin/sh\n")
+        executable.chmod(0o755)
+        path = self._config(tmp_path, "{name: test, cmd: echo, requires: [fnug-test-binary]}", "path_prepend: [bin]\n")
+
+        assert load_config(path).commands[0].requires == ["fnug-test-binary"]
+
+
+class TestEnvFile:
+    def test_read_env_file(self, tmp_path: Path):
+        env_file = tmp_path / ".env"
+        env_file.write_text("# comment\n\nFOO=bar\nexport QUOTED=\"hello world\"\nSINGLE='a=b'\n")
+
+        assert read_env_file(env_file) == {"FOO": "bar", "QUOTED": "hello world", "SINGLE": "a=b"}
+
+    def test_missing_file(self, tmp_path: Path):
+        with pytest.raises(ValueError, match="Env file .* does not exist"):
+            read_env_file(tmp_path / ".env")
+
+    def test_group_and_command_env_files(self, tmp_path: Path):
+        (tmp_path / ".env").write_text("FOO=group\nBAR=group\n")
+        (tmp_path / "backend").mkdir()
+        (tmp_path / "backend" / ".env").write_text("FOO=backend\n")
+        (tmp_path / "backend" / ".env.local").write_text("BAR=command\nBAZ=command\n")
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+env_file: [.env]
+commands:
+  - name: root
+    cmd: "true"
+  - name: backend
+    cmd: "true"
+    cwd: backend
+    env_file: [.env.local]
+    env:
+      BAZ: explicit
+""",
+        )
+
+        config = load_config(path)
+
+        assert config.commands[0].env == {"FOO": "group", "BAR": "group"}
+        assert config.commands[1].env == {"FOO": "backend", "BAR": "command", "BAZ": "explicit"}
+        assert config.commands[1].displayed_env() == {"FOO": "***", "BAR": "***", "BAZ": "explicit"}
+
+    def test_missing_env_file_in_config(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - name: test
+    cmd: "true"
+    env_file: [.env]
+""",
+        )
+
+        with pytest.raises(ValidationError, match="Env file .* does not exist"):
+            load_config(path)
+
+
+class TestGroupEnv:
+    def _load(self, tmp_path: Path, env_override: str) -> Config:
+        path = _write_config(
+            tmp_path,
+            f"""
+fnug_version: 0.1.0
+name: root
+env:
+  SHARED: root
+  ROOT: root
+{env_override}
+children:
+  - name: child
+    env:
+      SHARED: child
+    commands:
+      - name: test
+        cmd: "true"
+        env:
+          SHARED: command
+          OWN: command
+""",
+        )
+        return load_config(path)
+
+    def test_child_wins(self, tmp_path: Path):
+        config = self._load(tmp_path, "")
+
+        assert config.children[0].commands[0].env == {"SHARED": "command", "ROOT": "root", "OWN": "command"}
+
+    def test_parent_wins(self, tmp_path: Path):
+        config = self._load(tmp_path, "env_override: false")
+
+        assert config.children[0].commands[0].env == {"SHARED": "root", "ROOT": "root", "OWN": "command"}
+
+
+class TestFindCommand:
+    def _config(self, tmp_path: Path) -> Config:
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - {id: lint, name: ruff, cmd: ruff check}
+children:
+  - name: child
+    commands:
+      - {id: test, name: pytest, cmd: pytest}
+      - {id: other, name: ruff, cmd: ruff format}
+""",
+        )
+        return load_config(path)
+
+    def test_by_id(self, tmp_path: Path):
+        assert find_command(self._config(tmp_path), "test").cmd == "pytest"
+
+    def test_by_name(self, tmp_path: Path):
+        assert find_command(self._config(tmp_path), "pytest").id == "test"
+
+    def test_ambiguous_name(self, tmp_path: Path):
+        with pytest.raises(ValueError, match="Multiple commands are named 'ruff', use the id instead"):
+            find_command(self._config(tmp_path), "ruff")
+
+    def test_missing(self, tmp_path: Path):
+        with pytest.raises(ValueError, match="Could not find a command"):
+            find_command(self._config(tmp_path), "missing")
+
+
+class TestDuplicateCommands:
+    def test_same_cwd_and_cmd(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - {name: a, cmd: pytest}
+  - {name: b, cmd: pytest, cwd: ./}
+  - {name: c, cmd: pytest, cwd: backend}
+  - {name: d, cmd: ruff check}
+""",
+        )
+
+        assert duplicate_commands(load_config(path), tmp_path) == ["root > b: same cwd and cmd as root > a"]
+
+
+class TestFormatConfig:
+    def test_sorted_and_indented(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+name: root
+fnug_version: 0.1.0
+commands:
+- name: test
+  cmd: "echo hello\\necho world\\n"
+  scrollback: 100
+""",
+        )
+
+        assert format_config(path) == (
+            "commands:\n"
+            "  - cmd: |\n"
+            "      echo hello\n"
+            "      echo world\n"
+            "    name: test\n"
+            "    scrollback: 100\n"
+            "fnug_version: 0.1.0\n"
+            "name: root\n"
+        )
+
+    def test_formatting_is_stable(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a, cmd: b, tab_width: 4}]")
+
+        path.write_text(format_config(path))
+
+        assert format_config(path) == path.read_text()
+
+    def test_invalid_config(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a}]\n")
+
+        with pytest.raises(ValidationError):
+            format_config(path)
+
+    def test_generators_and_host_checks_are_skipped(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands: [{name: a, cmd: b, requires: [fnug-missing-binary], env_file: [missing.env]}]
+children: [{name: generated, generate: touch generated.txt}]
+""",
+        )
+
+        assert "generate: touch generated.txt" in format_config(path)
+        assert not (tmp_path / "generated.txt").exists()
+
+
+class TestHooks:
+    def test_hooks_are_left_to_the_shell(self):
+        command = ConfigCommand(name="test", cmd="pytest", before="docker compose up -d $FNUG_SERVICE")
+
+        assert command.before == "docker compose up -d $FNUG_SERVICE"
+
+    def test_run_subprocess(self, tmp_path: Path):
+        command = ConfigCommand(name="test", cmd="true")
+
+        assert command.run_subprocess("test -d .", tmp_path, {}) == 0
+        assert command.run_subprocess("exit 3", tmp_path, {}) == 3
+
+
+class TestErrorLocations:
+    def test_validation_error(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """fnug_version: 0.1.0
+name: root
+commands:
+  - name: first
+    cmd: "true"
+  - name: second
+    scrollback: many
+""",
+        )
+
+        with pytest.raises(ValidationError) as error:
+            load_config(path)
+
+        lines = format_validation_error(error.value, path).splitlines()
+        assert lines[0] == f"{path}:6:5: commands.1.cmd: Field required"
+        assert lines[1].startswith(f"{path}:7:17: commands.1.scrollback: Input should be a valid integer")
+
+    def test_yaml_syntax_error(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: [root\n")
+
+        with pytest.raises(ValueError, match=r"\.fnug\.yaml:3:1: "):
+            load_config(path)
+
+    def test_json_syntax_error(self, tmp_path: Path):
+        path = tmp_path / ".fnug.json"
+        path.write_text('{"fnug_version": "0.1.0",\n "name": }')
+
+        with pytest.raises(ValueError, match=r"\.fnug\.json:2:10: Expecting value"):
+            load_config(path)
+
+
+class TestUnknownKeys:
+    def test_typo_suggestion(self, tmp_path: Path):
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ncommmands: [{name: a, cmd: b}]\n")
+
+        with pytest.raises(ValidationError, match="Unknown keys: 'commmands' \\(did you mean 'commands'\\?\\)"):
+            load_config(path)
+
+    def test_nested_unknown_key(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a, cmd: b, auto: {always: true, foo: 1}}]\n"
+        )
+
+        with pytest.raises(ValidationError, match="Unknown keys: 'foo' "):
+            load_config(path)
+
+    def test_included_config_can_have_version(self, tmp_path: Path):
+        (tmp_path / "other.yaml").write_text("fnug_version: 0.1.0\nname: other\ncommands: [{name: a, cmd: b}]\n")
+        path = _write_config(tmp_path, "fnug_version: 0.1.0\nname: root\ninclude: [other.yaml]\n")
+
+        assert load_config(path).children[0].name == "other"
+
+
+class TestDisabled:
+    def test_disabled_commands_and_groups_are_skipped(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - {id: lint, name: ruff, cmd: ruff check}
+  - {id: flaky, name: flaky, cmd: ./flaky.sh, disabled: true}
+children:
+  - name: child
+    disabled: true
+    commands:
+      - {id: test, name: pytest, cmd: pytest}
+""",
+        )
+
+        assert [command.id for command in iter_commands(load_config(path))] == ["lint"]
+
+    def test_disabled_commands_are_not_checked(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path,
+            """
+fnug_version: 0.1.0
+name: root
+commands:
+  - {name: lint, cmd: ruff check}
+  - {name: copy, cmd: ruff check, disabled: true}
+  - {name: missing, cmd: echo, cwd: missing, requires: [fnug-missing-binary], env_file: [.env], disabled: true}
+""",
+        )
+
+        config = load_config(path)
+
+        assert validate_config(config, tmp_path) == []
+        assert duplicate_commands(config, tmp_path) == []
+
+    def test_everything_disabled(self, tmp_path: Path):
+        path = _write_config(
+            tmp_path, "fnug_version: 0.1.0\nname: root\ncommands: [{name: a, cmd: b, disabled: true}]\n"
+        )
+
+        with pytest.raises(ValidationError, match="All commands are disabled"):
+            load_config(path)
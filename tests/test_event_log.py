@@ -0,0 +1,39 @@
+import json
+from pathlib import Path
+
+from fnug.event_log import EventLog
+
+
+class TestEventLog:
+    def test_write(self, tmp_path: Path):
+        path = tmp_path / "events.jsonl"
+        event_log = EventLog(path)
+
+        event_log.write("start", id="1", cwd=tmp_path)
+        event_log.write("status", id="1", status="success")
+
+        records = [json.loads(line) for line in path.read_text().splitlines()]
+        assert [record["event"] for record in records] == ["start", "status"]
+        assert records[0]["cwd"] == str(tmp_path)
+        assert records[1]["status"] == "success"
+        assert "timestamp" in records[0]
+
+    def test_disabled(self, tmp_path: Path):
+        EventLog().write("start", id="1")
+
+        assert list(tmp_path.iterdir()) == []
+
+    def test_rotation(self, tmp_path: Path):
+        path = tmp_path / "events.jsonl"
+        event_log = EventLog(path, max_bytes=1, keep=2)
+
+        for index in range(4):
+            event_log.write("start", id=str(index))
+
+        def ids(file: Path) -> list[str]:
+            return [json.loads(line)["id"] for line in file.read_text().splitlines()]
+
+        assert ids(path) == ["3"]
+        assert ids(tmp_path / "events.jsonl.1") == ["2"]
+        assert ids(tmp_path / "events.jsonl.2") == ["1"]
+        assert not (tmp_path / "events.jsonl.3").exists()
@@ -0,0 +1,82 @@
+from pathlib import Path
+
+import pytest
+from pygit2 import Repository, Signature, init_repository
+
+from fnug.git import StatusCache, changed_files, detect_repo_changes, repo_root
+
+
+def _commit(repo: Repository, message: str):
+    repo.index.add_all()
+    repo.index.write()
+    signature = Signature("fnug", "fnug@example.com")
+    parents = [] if repo.head_is_unborn else [repo.head.target]
+    repo.create_commit("HEAD", signature, signature, message, repo.index.write_tree(), parents)
+
+
+def _write(path: Path, content: str = ""):
+    path.parent.mkdir(parents=True, exist_ok=True)
+    path.write_text(content)
+
+
+class TestChangedFiles:
+    def test_only_files_inside_path(self, tmp_path: Path):
+        repo = init_repository(tmp_path)
+        _write(tmp_path / "README.md")
+        _commit(repo, "initial")
+        _write(tmp_path / "docs" / "index.md")
+        _write(tmp_path / "src" / "notes.md")
+
+        assert changed_files(tmp_path / "docs", [r"\.md$"]) == ["docs/index.md"]
+        assert sorted(changed_files(tmp_path, [r"\.md$"])) == ["docs/index.md", "src/notes.md"]
+
+    def test_git_ref(self, tmp_path: Path):
+        repo = init_repository(tmp_path)
+        _write(tmp_path / "a.txt", "a")
+        _commit(repo, "initial")
+        _write(tmp_path / "a.txt", "changed")
+        _write(tmp_path / "b.txt")
+        _commit(repo, "second")
+
+        assert changed_files(tmp_path) == []
+        assert sorted(changed_files(tmp_path, ref="HEAD~1")) == ["a.txt", "b.txt"]
+
+    def test_unknown_git_ref(self, tmp_path: Path):
+        repo = init_repository(tmp_path)
+        _write(tmp_path / "a.txt")
+        _commit(repo, "initial")
+
+        with pytest.raises(ValueError, match="Could not resolve git ref 'missing'"):
+            changed_files(tmp_path, ref="missing")
+
+    def test_staged(self, tmp_path: Path):
+        repo = init_repository(tmp_path)
+        _write(tmp_path / "a.txt", "a")
+        _commit(repo, "initial")
+        _write(tmp_path / "a.txt", "changed")
+        _write(tmp_path / "b.txt")
+        repo.index.add("b.txt")
+        repo.index.write()
+
+        assert changed_files(tmp_path, staged=True) == ["b.txt"]
+        assert sorted(changed_files(tmp_path)) == ["a.txt", "b.txt"]
+
+    def test_status_cache(self, tmp_path: Path):
+        repo = init_repository(tmp_path)
+        _write(tmp_path / "a.txt")
+        _commit(repo, "initial")
+        status_cache: StatusCache = {}
+        assert changed_files(tmp_path, status_cache=status_cache) == []
+
+        # the status is reused within a pass, but new edits are seen on the next one
+        _write(tmp_path / "b.txt")
+        assert changed_files(tmp_path, status_cache=status_cache) == []
+        assert changed_files(tmp_path, status_cache={}) == ["b.txt"]
+        assert changed_files(tmp_path) == ["b.txt"]
+
+    def test_not_a_repository(self, tmp_path: Path):
+        _write(tmp_path / "a.txt")
+
+        assert changed_files(tmp_path) == []
+        assert not detect_repo_changes(tmp_path)
+        assert repo_root(tmp_path) is None
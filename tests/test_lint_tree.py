@@ -3,7 +3,14 @@ from unittest.mock import Mock
 from rich.text import Text
 from textual.widgets._tree import NodeID, Tree, TreeNode
 
-from fnug.ui.components.lint_tree import LintTreeDataType, select_node, toggle_select_node, update_node
+from fnug.ui.components.lint_tree import (
+    LintTreeDataType,
+    format_duration,
+    select_node,
+    sort_nodes,
+    toggle_select_node,
+    update_node,
+)
 
 
 def _create_node(parent=None):
@@ -88,3 +95,58 @@ class TestToggleSelectNode:
 
         assert node.data.selected is True
         assert child.data.selected is True
+
+
+def _create_command_node(parent, node_id, name, status=None):
+    data = LintTreeDataType(str(node_id), name, "command", status=status)
+    node = TreeNode(Tree(""), parent, NodeID(node_id), Text(name), data=data)
+    parent._children.append(node)
+    return node
+
+
+class TestSortNodes:
+    def _names(self, node):
+        return [child.data.name for child in node.children if child.data]
+
+    def test_sort_by_name_and_back(self):
+        root = _create_node()
+        _create_command_node(root, 2, "lint")
+        _create_command_node(root, 3, "format")
+        _create_command_node(root, 4, "Build")
+
+        sort_nodes(root, "name")
+        assert self._names(root) == ["Build", "format", "lint"]
+
+        sort_nodes(root, "config")
+        assert self._names(root) == ["lint", "format", "Build"]
+
+    def test_sort_by_status(self):
+        root = _create_node()
+        _create_command_node(root, 2, "success", "success")
+        _create_command_node(root, 3, "pending")
+        _create_command_node(root, 4, "failure", "failure")
+        _create_command_node(root, 5, "running", "running")
+        _create_command_node(root, 6, "other failure", "failure")
+
+        sort_nodes(root, "status")
+
+        assert self._names(root) == ["failure", "other failure", "running", "pending", "success"]
+
+    def test_sort_by_duration(self):
+        root = _create_node()
+        for node_id, name, duration in [(2, "fast", 1), (3, "never run", None), (4, "slow", 30), (5, "medium", 5)]:
+            node = _create_command_node(root, node_id, name)
+            if duration is not None:
+                node.data.started, node.data.finished = 100, 100 + duration
+
+        sort_nodes(root, "duration")
+
+        assert self._names(root) == ["slow", "medium", "fast", "never run"]
+
+
+class TestFormatDuration:
+    def test_seconds(self):
+        assert format_duration(12.7) == "12s"
+
+    def test_minutes(self):
+        assert format_duration(65) == "1m05s"
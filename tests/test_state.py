@@ -0,0 +1,29 @@
+from pathlib import Path
+
+from fnug.state import STATE_FILE_NAME, TreeState, load_state, save_state, state_path
+
+
+class TestState:
+    def test_roundtrip(self, tmp_path: Path):
+        path = tmp_path / STATE_FILE_NAME
+        state = TreeState(expanded=["root > group"], selected=["root > group > test"])
+
+        save_state(path, state)
+
+        assert load_state(path) == state
+
+    def test_missing_file(self, tmp_path: Path):
+        assert load_state(tmp_path / STATE_FILE_NAME) is None
+
+    def test_invalid_file(self, tmp_path: Path):
+        path = tmp_path / STATE_FILE_NAME
+        path.write_text("not json")
+
+        assert load_state(path) is None
+
+    def test_state_path(self, tmp_path: Path):
+        config = tmp_path / ".fnug.yaml"
+        config.write_text("")
+
+        assert state_path(config) == tmp_path / STATE_FILE_NAME
+        assert state_path(tmp_path) == tmp_path / STATE_FILE_NAME
@@ -0,0 +1,103 @@
+import asyncio
+from pathlib import Path
+
+import pytest
+from textual.geometry import Size
+
+from fnug.config import ConfigCommand
+from fnug.terminal_emulator import LineTruncator, TerminalEmulator
+
+
+class TestLineTruncator:
+    def test_disabled(self):
+        assert LineTruncator().feed("a" * 100) == "a" * 100
+
+    def test_short_lines(self):
+        assert LineTruncator(5).feed("abc\nabcde\r\n") == "abc\nabcde\r\n"
+
+    def test_truncate(self):
+        assert LineTruncator(3).feed("abcdef\nabc") == f"abc{LineTruncator.marker}\nabc"
+
+    def test_line_continues_between_chunks(self):
+        truncator = LineTruncator(4)
+
+        assert truncator.feed("abc") == "abc"
+        assert truncator.feed("def") == f"d{LineTruncator.marker}"
+        assert truncator.feed("ghi") == ""
+        assert truncator.feed("\nab") == "\nab"
+
+    def test_carriage_return_resets_length(self):
+        assert LineTruncator(3).feed("abc\rdef") == "abc\rdef"
+
+    def test_escape_sequences_are_not_counted(self):
+        truncator = LineTruncator(3)
+
+        assert truncator.feed("\x1b[31mabcdef\x1b[0m\n") == f"\x1b[31mabc{LineTruncator.marker}\x1b[0m\n"
+
+    def test_escape_sequence_split_between_chunks(self):
+        truncator = LineTruncator(3)
+
+        assert truncator.feed("ab\x1b[3") == "ab"
+        assert truncator.feed("1mcdef\x1b[0m") == f"\x1b[31mc{LineTruncator.marker}\x1b[0m"
+
+
+class TestTerminalEmulator:
+    def test_feed_only_shows_output(self):
+        emulator = TerminalEmulator(Size(20, 5))
+
+        emulator.feed(b"hello")
+
+        assert emulator.text().strip() == "hello"
+
+    def test_feed_character_split_between_reads(self):
+        emulator = TerminalEmulator(Size(20, 5))
+        data = "æøå ✔".encode()
+
+        emulator.feed(data[:1])
+        emulator.feed(data[1:6])
+        emulator.feed(data[6:])
+
+        assert emulator.text().strip() == "æøå ✔"
+
+    def test_min_size(self):
+        emulator = TerminalEmulator(Size(20, 5), min_size=Size(80, 0))
+
+        emulator.dimensions = Size(40, 10)
+
+        assert emulator.dimensions == Size(80, 10)
+        assert (emulator.screen.columns, emulator.screen.lines) == (80, 10)
+
+
+class TestRunWithHooks:
+    def test_hooks(self, tmp_path: Path):
+        emulator = TerminalEmulator(Size(20, 5))
+        command = ConfigCommand(
+            name="test", cmd="echo cmd >> log", before="echo before >> log", after="echo after >> log"
+        )
+        results: list[bool] = []
+
+        assert asyncio.run(emulator.run_with_hooks(command, tmp_path, on_done=results.append)) is True
+        assert results == [True]
+        assert (tmp_path / "log").read_text().split() == ["before", "cmd", "after"]
+
+    def test_failing_before_skips_the_command(self, tmp_path: Path):
+        emulator = TerminalEmulator(Size(20, 5))
+        command = ConfigCommand(name="test", cmd="echo cmd >> log", before="exit 1", after="echo after >> log")
+
+        assert asyncio.run(emulator.run_with_hooks(command, tmp_path)) is False
+        assert (tmp_path / "log").read_text().split() == ["after"]
+
+    def test_after_runs_when_stopped(self, tmp_path: Path):
+        emulator = TerminalEmulator(Size(20, 5), kill_grace=1.0)
+        command = ConfigCommand(name="test", cmd="sleep 10", after="echo after >> log")
+
+        async def stop():
+            task = asyncio.create_task(emulator.run_with_hooks(command, tmp_path))
+            await asyncio.sleep(0.2)
+            task.cancel()
+            with pytest.raises(asyncio.CancelledError):
+                await task
+
+        asyncio.run(stop())
+
+        assert (tmp_path / "log").read_text().split() == ["after"]